@@ -0,0 +1,147 @@
+//! Embeds every route in `routes/` into the binary.
+//!
+//! Each route is checked first with [`jerryio::load`], so its statements, JerryIO path data and
+//! actions file (if it has one) have to parse and every point it drives through has to be on the
+//! field. A broken export fails
+//! the build instead of failing on the field. The checked routes are written to
+//! `$OUT_DIR/routes.rs`, which `route.rs` includes.
+
+#[allow(dead_code)]
+#[path = "src/jerryio.rs"]
+mod jerryio;
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+fn main() {
+    println!("cargo::rerun-if-changed=routes");
+    println!("cargo::rerun-if-changed=src/jerryio.rs");
+
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let mut files = fs::read_dir(manifest_dir.join("routes"))
+        .expect("couldn't read routes/")
+        .map(|entry| entry.expect("couldn't read routes/").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut problems = Vec::new();
+    let mut constants = String::new();
+    let mut registered = Vec::new();
+
+    for file in &files {
+        let actions = file.with_extension(jerryio::ACTIONS_EXTENSION);
+        let actions = actions.exists().then_some(actions);
+        let start = match check(file, actions.as_deref()) {
+            Ok(start) => start,
+            Err(e) => {
+                problems.push(format!("{}: {e}", file.display()));
+                continue;
+            }
+        };
+
+        let name = file.file_stem().unwrap().to_string_lossy();
+        let constant = constant_name(&name);
+        let start = match start {
+            Some((x, y, heading)) => format!(
+                "Some(crate::placement::StartPose::new({x:?}, {y:?}, evian::math::Angle::from_degrees({heading:?})))"
+            ),
+            None => "None".to_string(),
+        };
+        let actions = match actions {
+            Some(actions) => format!("Some(include_str!({:?}))", actions.display().to_string()),
+            None => "None".to_string(),
+        };
+
+        // writing to a string can't fail
+        let _ = writeln!(
+            constants,
+            "pub const {constant}: crate::route::EmbeddedRoute = crate::route::EmbeddedRoute {{\n    \
+                 name: {name:?},\n    \
+                 text: include_str!({:?}),\n    \
+                 actions: {actions},\n    \
+                 start: {start},\n\
+             }};\n",
+            file.display().to_string()
+        );
+        registered.push(format!(
+            "        crate::placement::PlacedRoute {{\n            \
+                 route: autons::simple::Route::new({:?}, |jodio| {{\n                \
+                     Box::pin(crate::route::run_embedded(jodio, &{constant}))\n            \
+                 }}),\n            \
+                 start: {constant}.start,\n        \
+             }},",
+            display_name(&name)
+        ));
+    }
+
+    if !problems.is_empty() {
+        panic!("invalid routes:\n{}", problems.join("\n"));
+    }
+
+    let generated = format!(
+        "// Generated by build.rs from the files in routes/.\n\n\
+         {constants}\
+         /// Every route in `routes/`, for the route selector.\n\
+         pub fn embedded() -> [crate::placement::PlacedRoute<crate::Jodio>; {}] {{\n    \
+             [\n{}\n    ]\n\
+         }}\n",
+        registered.len(),
+        registered.join("\n")
+    );
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(out_dir.join("routes.rs"), generated).expect("couldn't write routes.rs");
+}
+
+/// Checks a route file along with its actions file, returning the pose its first path starts at.
+fn check(file: &Path, actions: Option<&Path>) -> Result<Option<(f64, f64, f64)>, String> {
+    let text = fs::read_to_string(file).map_err(|e| e.to_string())?;
+    let actions = actions
+        .map(fs::read_to_string)
+        .transpose()
+        .map_err(|e| format!("actions file: {e}"))?;
+    let route = jerryio::load(&text, actions.as_deref()).map_err(|e| e.to_string())?;
+
+    Ok(route.start_pose())
+}
+
+/// Turns a file name like `skills-route` into a constant name like `SKILLS_ROUTE`.
+fn constant_name(name: &str) -> String {
+    let constant = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    if constant.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("ROUTE_{constant}")
+    } else {
+        constant
+    }
+}
+
+/// Turns a file name like `skills-route` into the name shown in the selector, like `Skills`.
+fn display_name(name: &str) -> String {
+    let name = name.strip_suffix("-route").unwrap_or(name);
+
+    name.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,173 @@
+//! Drives a simulated robot through a skills-length run with drifting odometry and noisy distance
+//! sensors, and compares how far raw odometry and the particle filter end up from the true pose.
+//! Also reports how long each filter update takes.
+//!
+//! ```sh
+//! cargo run --release --manifest-path host/Cargo.toml --bin mcl-sim -- [seed] [particles]
+//! ```
+
+use std::{
+    f64::consts::{FRAC_PI_2, PI},
+    time::{Duration, Instant},
+};
+
+use rainbots_host::{
+    ekf::{ANGULAR_NOISE, LINEAR_NOISE, Pose, wrap},
+    field,
+    mcl::{Beam, MAX_RANGE, Mcl},
+    rng::Rng,
+};
+
+const DT: f64 = 0.01;
+const DURATION: f64 = 60.0;
+const UPDATE_INTERVAL: usize = 5;
+
+// odometry error model
+const WHEEL_SCALE_ERROR: f64 = 0.03;
+const HEADING_DRIFT: f64 = 0.005;
+const ODOMETRY_NOISE: f64 = 0.02;
+
+// distance sensor error model
+const SENSOR_NOISE: f64 = 0.5;
+const SENSOR_OUTLIER_CHANCE: f64 = 0.05;
+
+const BEAMS: [Beam; 3] = [
+    Beam {
+        x: -6.0,
+        y: 0.0,
+        angle: PI,
+    },
+    Beam {
+        x: 0.0,
+        y: 6.0,
+        angle: FRAC_PI_2,
+    },
+    Beam {
+        x: 0.0,
+        y: -6.0,
+        angle: -FRAC_PI_2,
+    },
+];
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let seed = args.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+    let count = args.next().and_then(|s| s.parse().ok()).unwrap_or(250);
+    let mut rng = Rng::new(seed);
+
+    let start = Pose {
+        x: -60.0,
+        y: 18.0,
+        heading: 0.0,
+    };
+    let mut truth = start;
+    let mut odometry = start;
+    let mut mcl = Mcl::new(
+        start,
+        count,
+        1.0,
+        LINEAR_NOISE,
+        ANGULAR_NOISE,
+        SENSOR_NOISE * 2.0,
+        seed,
+    );
+
+    let mut odometry_error = 0.0;
+    let mut filter_error = 0.0;
+    let mut steps = 0;
+    let mut update_time = Duration::ZERO;
+    let mut updates = 0;
+
+    // motion accumulated since the last filter update
+    let mut pending = (0.0, 0.0);
+
+    let mut time = 0.0;
+    while time < DURATION {
+        // wander around the field, turning back towards the center when near the walls
+        let speed = 24.0;
+        let to_center = wrap((-truth.y).atan2(-truth.x) - truth.heading);
+        let turn_rate = if truth.x.hypot(truth.y) > 45.0 {
+            1.5 * to_center.signum()
+        } else {
+            0.6 * (time * 0.3).sin()
+        };
+
+        let forward = speed * DT;
+        let turn = turn_rate * DT;
+        truth = step(truth, forward, turn);
+
+        let measured_forward = forward * (1.0 + WHEEL_SCALE_ERROR) + rng.gaussian(ODOMETRY_NOISE);
+        let measured_turn = turn + HEADING_DRIFT * DT + rng.gaussian(ODOMETRY_NOISE * DT);
+        odometry = step(odometry, measured_forward, measured_turn);
+
+        // the filter runs slower than odometry, so motion is batched between updates
+        pending.0 += measured_forward;
+        pending.1 += measured_turn;
+
+        if steps % UPDATE_INTERVAL == 0 {
+            let readings = BEAMS
+                .iter()
+                .filter_map(|beam| {
+                    let distance = read(&mut rng, truth, beam)?;
+                    Some((*beam, distance))
+                })
+                .collect::<Vec<_>>();
+
+            let started = Instant::now();
+            mcl.predict(pending.0, 0.0, pending.1);
+            mcl.update(&readings);
+            update_time += started.elapsed();
+            updates += 1;
+
+            pending = (0.0, 0.0);
+        }
+
+        odometry_error += distance(odometry, truth).powi(2);
+        filter_error += distance(mcl.estimate(), truth).powi(2);
+        steps += 1;
+        time += DT;
+    }
+
+    println!("seed {seed}, {count} particles, {DURATION} s");
+    println!(
+        "rms position error: odometry {:.2} in, filter {:.2} in",
+        (odometry_error / steps as f64).sqrt(),
+        (filter_error / steps as f64).sqrt()
+    );
+    println!(
+        "final error: odometry {:.2} in, filter {:.2} in (spread {:.2} in)",
+        distance(odometry, truth),
+        distance(mcl.estimate(), truth),
+        mcl.spread()
+    );
+    println!("average update: {:?}", update_time / updates);
+}
+
+/// Simulates a distance sensor reading, returning `None` if nothing is in range.
+fn read(rng: &mut Rng, pose: Pose, beam: &Beam) -> Option<f64> {
+    let (sin, cos) = pose.heading.sin_cos();
+    let origin = [
+        pose.x + beam.x * cos - beam.y * sin,
+        pose.y + beam.x * sin + beam.y * cos,
+    ];
+
+    // something that isn't on the map, like another robot
+    if rng.uniform() < SENSOR_OUTLIER_CHANCE {
+        return Some(rng.uniform() * MAX_RANGE);
+    }
+
+    let distance = field::raycast(origin, pose.heading + beam.angle)? + rng.gaussian(SENSOR_NOISE);
+    (distance < MAX_RANGE).then_some(distance)
+}
+
+fn step(pose: Pose, forward: f64, turn: f64) -> Pose {
+    Pose {
+        x: pose.x + forward * pose.heading.cos(),
+        y: pose.y + forward * pose.heading.sin(),
+        heading: wrap(pose.heading + turn),
+    }
+}
+
+fn distance(a: Pose, b: Pose) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
@@ -0,0 +1,232 @@
+//! Renders an odometry trace recorded on the robot over the Push Back field as an SVG.
+//!
+//! The path is colored by speed (blue is slow, red is fast), with a tick every half second showing
+//! which way the robot was facing. Planned waypoints are drawn as numbered orange circles.
+//!
+//! ```sh
+//! cargo run --manifest-path host/Cargo.toml --bin trace-plot -- trace_skills.csv [out.svg]
+//! ```
+
+use std::{fmt::Write, fs, path::PathBuf, process::ExitCode};
+
+use rainbots_host::{
+    field::{self, WALL},
+    trace::{HEADER, Sample, Trace, Waypoint},
+};
+
+/// Pixels per inch.
+const SCALE: f64 = 4.0;
+const MARGIN: f64 = 20.0;
+const LEGEND_HEIGHT: f64 = 40.0;
+
+/// Seconds between heading ticks.
+const TICK_INTERVAL: f64 = 0.5;
+const TICK_LENGTH: f64 = 4.0;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(input) = args.next().map(PathBuf::from) else {
+        eprintln!("usage: trace-plot <trace.csv> [out.svg]");
+        return ExitCode::FAILURE;
+    };
+    let output = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| input.with_extension("svg"));
+
+    let trace = match fs::read_to_string(&input)
+        .map_err(|e| e.to_string())
+        .and_then(|csv| parse(&csv))
+    {
+        Ok(trace) => trace,
+        Err(e) => {
+            eprintln!("couldn't read {}: {e}", input.display());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = fs::write(&output, render(&trace)) {
+        eprintln!("couldn't write {}: {e}", output.display());
+        return ExitCode::FAILURE;
+    }
+
+    println!(
+        "plotted {} samples and {} waypoints to {}",
+        trace.samples.len(),
+        trace.waypoints.len(),
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
+
+fn parse(csv: &str) -> Result<Trace, String> {
+    let mut trace = Trace::default();
+
+    for (number, line) in csv
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+    {
+        if let Some(route) = line.strip_prefix("# route:") {
+            trace.route = route.trim().to_string();
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') || line == HEADER {
+            continue;
+        }
+
+        let fields = line.split(',').collect::<Vec<_>>();
+        let field = |index: usize| -> Result<f64, String> {
+            fields
+                .get(index)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| format!("line {number}: bad or missing column {}", index + 1))
+        };
+
+        match fields[0] {
+            "sample" => trace.samples.push(Sample {
+                time: field(1)?,
+                x: field(2)?,
+                y: field(3)?,
+                heading: field(4)?,
+                linear_velocity: field(5)?,
+                angular_velocity: field(6)?,
+            }),
+            "waypoint" => trace.waypoints.push(Waypoint {
+                time: field(1)?,
+                x: field(2)?,
+                y: field(3)?,
+            }),
+            kind => return Err(format!("line {number}: unknown row kind `{kind}`")),
+        }
+    }
+
+    Ok(trace)
+}
+
+/// Converts field coordinates to SVG coordinates, where y points down.
+fn to_svg(x: f64, y: f64) -> (f64, f64) {
+    (MARGIN + (x + WALL) * SCALE, MARGIN + (WALL - y) * SCALE)
+}
+
+fn render(trace: &Trace) -> String {
+    let size = 2.0 * (MARGIN + WALL * SCALE);
+    let mut svg = String::new();
+
+    // writing to a string can't fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{}" font-family="monospace" font-size="12">"#,
+        size + LEGEND_HEIGHT
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect width="100%" height="100%" fill="#ffffff"/><rect x="{MARGIN}" y="{MARGIN}" width="{0}" height="{0}" fill="#d8d8d8"/>"##,
+        2.0 * WALL * SCALE
+    );
+
+    for segment in field::SEGMENTS {
+        let (x1, y1) = to_svg(segment.start[0], segment.start[1]);
+        let (x2, y2) = to_svg(segment.end[0], segment.end[1]);
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="#303030" stroke-width="3"/>"##
+        );
+    }
+
+    // planned path
+    if !trace.waypoints.is_empty() {
+        let points = trace
+            .waypoints
+            .iter()
+            .map(|waypoint| {
+                let (x, y) = to_svg(waypoint.x, waypoint.y);
+                format!("{x:.1},{y:.1}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            svg,
+            r##"<polyline points="{points}" fill="none" stroke="#ff9800" stroke-width="1.5" stroke-dasharray="6 4"/>"##
+        );
+    }
+
+    // recorded path, colored by speed
+    let max_speed = trace
+        .samples
+        .iter()
+        .map(|sample| sample.linear_velocity.abs())
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+    for pair in trace.samples.windows(2) {
+        let (x1, y1) = to_svg(pair[0].x, pair[0].y);
+        let (x2, y2) = to_svg(pair[1].x, pair[1].y);
+        let hue = 240.0 * (1.0 - pair[1].linear_velocity.abs() / max_speed);
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="hsl({hue:.0},90%,45%)" stroke-width="2.5" stroke-linecap="round"/>"#
+        );
+    }
+
+    let mut next_tick = 0.0;
+    for sample in &trace.samples {
+        if sample.time < next_tick {
+            continue;
+        }
+        next_tick = sample.time + TICK_INTERVAL;
+
+        let heading = sample.heading.to_radians();
+        let (x1, y1) = to_svg(sample.x, sample.y);
+        let (x2, y2) = to_svg(
+            sample.x + TICK_LENGTH * heading.cos(),
+            sample.y + TICK_LENGTH * heading.sin(),
+        );
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="#000000" stroke-width="1"/>"##
+        );
+    }
+
+    if let (Some(first), Some(last)) = (trace.samples.first(), trace.samples.last()) {
+        let (x, y) = to_svg(first.x, first.y);
+        let _ = writeln!(
+            svg,
+            r##"<circle cx="{x:.1}" cy="{y:.1}" r="5" fill="#2e7d32"/>"##
+        );
+        let (x, y) = to_svg(last.x, last.y);
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{:.1}" y="{:.1}" width="10" height="10" fill="#c62828"/>"##,
+            x - 5.0,
+            y - 5.0
+        );
+    }
+
+    for (i, waypoint) in trace.waypoints.iter().enumerate() {
+        let (x, y) = to_svg(waypoint.x, waypoint.y);
+        let _ = writeln!(
+            svg,
+            r##"<circle cx="{x:.1}" cy="{y:.1}" r="4" fill="none" stroke="#ff9800" stroke-width="2"/><text x="{:.1}" y="{:.1}" fill="#e65100">{}</text>"##,
+            x + 6.0,
+            y - 6.0,
+            i + 1
+        );
+    }
+
+    let duration = trace.samples.last().map_or(0.0, |sample| sample.time);
+    let _ = writeln!(
+        svg,
+        r##"<text x="{MARGIN}" y="{:.1}" fill="#000000">route: {} | {duration:.2} s | max speed {max_speed:.1} in/s | start ● end ■</text>"##,
+        size + LEGEND_HEIGHT / 2.0,
+        escape(&trace.route)
+    );
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
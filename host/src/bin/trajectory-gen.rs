@@ -0,0 +1,114 @@
+//! Generates a trajectory from the first path in a JerryIO route file, so it can be checked before
+//! running it on the robot.
+//!
+//! The trajectory is written in the same CSV format as odometry traces, so `trace-plot` draws it
+//! colored by speed, and plotting a trace recorded while the robot drove it next to this shows how
+//! closely it was tracked.
+//!
+//! ```sh
+//! cargo run --manifest-path host/Cargo.toml --bin trajectory-gen -- routes/skills-route.txt [out.csv] [--reverse]
+//! ```
+
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use rainbots_host::{
+    jerryio,
+    path::{self, PathSegment},
+    trace::{Sample, Trace, Waypoint},
+    trajectory::{self, Trajectory},
+};
+
+fn main() -> ExitCode {
+    let (flags, args): (Vec<String>, Vec<String>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let reverse = flags.iter().any(|flag| flag == "--reverse");
+    let mut args = args.into_iter();
+
+    let Some(input) = args.next().map(PathBuf::from) else {
+        eprintln!("usage: trajectory-gen <route.txt> [out.csv] [--reverse]");
+        return ExitCode::FAILURE;
+    };
+    let output = args
+        .next()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| input.with_extension("csv"));
+
+    let route = match fs::read_to_string(&input)
+        .map_err(|e| e.to_string())
+        .and_then(|text| jerryio::load(&text, None).map_err(|e| e.to_string()))
+    {
+        Ok(route) => route,
+        Err(e) => {
+            eprintln!("couldn't read {}: {e}", input.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let Some(jerryio_path) = route.data.paths.first() else {
+        eprintln!("{} doesn't have a path", input.display());
+        return ExitCode::FAILURE;
+    };
+
+    let speed = jerryio_path.speed.map_or(1.0, |speed| speed / 100.0);
+    let segments = jerryio_path
+        .segments
+        .iter()
+        .map(|segment| PathSegment {
+            controls: segment
+                .controls
+                .iter()
+                .map(|control| [control.x, control.y])
+                .collect(),
+            speed,
+        })
+        .collect::<Vec<_>>();
+    let trajectory = Trajectory::generate(
+        &path::sample(&segments, path::SPACING),
+        trajectory::CONSTRAINTS,
+        reverse,
+    );
+
+    let trace = Trace {
+        route: format!("{} (generated)", jerryio_path.name),
+        samples: trajectory
+            .states
+            .iter()
+            .map(|state| Sample {
+                time: state.time,
+                x: state.x,
+                y: state.y,
+                heading: state.heading.to_degrees(),
+                linear_velocity: state.velocity,
+                angular_velocity: state.angular_velocity.to_degrees(),
+            })
+            .collect(),
+        waypoints: route
+            .waypoints()
+            .iter()
+            .map(|waypoint| Waypoint {
+                time: 0.0,
+                x: waypoint.x,
+                y: waypoint.y,
+            })
+            .collect(),
+    };
+
+    let written = fs::File::create(&output).and_then(|file| trace.write_csv(file));
+    if let Err(e) = written {
+        eprintln!("couldn't write {}: {e}", output.display());
+        return ExitCode::FAILURE;
+    }
+
+    let max_velocity = trajectory
+        .states
+        .iter()
+        .map(|state| state.velocity.abs())
+        .fold(0.0, f64::max);
+    println!(
+        "generated {} states over {:.2} s (max {max_velocity:.1} in/s) to {}",
+        trajectory.states.len(),
+        trajectory.duration(),
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,18 @@
+#[path = "../../src/ekf.rs"]
+pub mod ekf;
+#[path = "../../src/field.rs"]
+pub mod field;
+#[path = "../../src/jerryio.rs"]
+pub mod jerryio;
+#[path = "../../src/mcl.rs"]
+pub mod mcl;
+#[path = "../../src/path.rs"]
+pub mod path;
+#[path = "../../src/profile.rs"]
+pub mod profile;
+#[path = "../../src/rng.rs"]
+pub mod rng;
+#[path = "../../src/trace.rs"]
+pub mod trace;
+#[path = "../../src/trajectory.rs"]
+pub mod trajectory;
@@ -1,54 +1,109 @@
 use std::time::Duration;
 
-use evian::{math::Vec2, motion::Basic, prelude::*};
+use evian::{
+    math::{Angle, Vec2},
+    prelude::*,
+};
 use vexide::time::sleep;
 
-use crate::{Jodio, consts, intake::Command};
+use crate::{
+    Jodio, consts,
+    intake::Command,
+    path::{self, PathSegment},
+    placement::StartPose,
+    trajectory::{Constraints, Trajectory},
+    watchdog::{MotionResult, Outcome},
+};
 
 type Point = Vec2<f64>;
 
+// TODO: correct y coord
+pub const AWP_START: StartPose = StartPose::new(-44.866, 0.0, Angle::from_degrees(90.0));
+/// Where the safe route starts on the right. The left route starts at this pose mirrored.
+pub const SAFE_START: StartPose = StartPose::new(-44.866, -14.0, Angle::from_degrees(90.0));
+
+/// Moves tracking to where the route expects the robot to have been placed.
+pub fn place(jodio: &mut Jodio, start: StartPose) {
+    jodio.dt.tracking.set_position(start.position);
+    jodio.dt.tracking.set_heading(start.heading);
+    // being placed on the field isn't a disturbance
+    jodio.monitor.take_disturbance();
+}
+
+/// Where the robot would end up if it turned to `heading` and then drove `distance` from where it
+/// is now.
+fn ahead(jodio: &Jodio, heading: Angle, distance: f64) -> Point {
+    let position = jodio.dt.tracking.position();
+    Vec2::new(
+        position.x + heading.cos() * distance,
+        position.y + heading.sin() * distance,
+    )
+}
+
+/// Stops trusting the tracked pose if the robot slipped, was pushed, or hit something since the
+/// last check, or if the motion that just ran stalled or was aborted, and relocalizes against the
+/// walls if it can.
+pub fn recover(jodio: &mut Jodio, result: &MotionResult) {
+    let disturbed = jodio.monitor.take_disturbance().is_some();
+    let stuck = matches!(result.outcome, Outcome::Stalled | Outcome::Aborted);
+    if !disturbed && !stuck {
+        return;
+    }
+
+    jodio.dt.tracking.lower_confidence(
+        consts::DISTURBANCE_POSITION_ERROR,
+        consts::DISTURBANCE_HEADING_ERROR,
+    );
+    jodio.relocalizer.relocalize(&mut jodio.dt.tracking);
+}
+
 pub async fn awp(jodio: &mut Jodio) {
-    let mut basic = Basic {
-        linear_controller: consts::LINEAR_PID,
-        angular_controller: consts::ANGULAR_PID,
-        linear_tolerances: consts::LINEAR_TOLERANCES,
-        angular_tolerances: consts::ANGULAR_TOLERANCES,
-        // TODO: add timeout
-        timeout: None,
-    };
+    let _trace = jodio.trace.start("awp");
 
-    jodio.dt.tracking.set_position((-44.866, 0.0)); // TODO: correct y coord
-    jodio.dt.tracking.set_heading(90.0.deg());
+    place(jodio, AWP_START);
 
     // PHASE 1: Long goal scoring
 
     jodio.intake_command.set(Command::Collect);
 
     // drive down to right matchloader and long goal
-    basic.drive_distance(&mut jodio.dt, 30.119).await;
-    basic.turn_to_heading(&mut jodio.dt, 270.0.deg()).await;
+    jodio.motion.drive_distance(&mut jodio.dt, 30.119).await;
     jodio.matchloader.extend();
-    basic.drive_distance(&mut jodio.dt, 9.583).await;
-    // wait for blocks to be collected from loader
-    sleep(Duration::from_millis(500)).await; // TODO: placeholder duration
+    let loader = ahead(jodio, 270.0.deg(), 9.583);
+    let at_loader = jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, loader, 270.0.deg(), false)
+        .await;
+    // wait for blocks to be collected from loader, if the robot made it there
+    if at_loader.settled() {
+        sleep(Duration::from_millis(500)).await; // TODO: placeholder duration
+    }
+    jodio.relocalizer.relocalize(&mut jodio.dt.tracking);
 
     // drive to long goal
-    basic.drive_distance(&mut jodio.dt, -23.861).await;
+    jodio.motion.drive_distance(&mut jodio.dt, -23.861).await;
+    jodio.relocalizer.relocalize(&mut jodio.dt.tracking);
     jodio.intake_command.set(Command::ScoreLong);
     sleep(Duration::from_millis(500)).await; // TODO: placeholder duration
 
     // PHASE 2: Middle goal scoring
 
     jodio.matchloader.retract();
-    // face line of blocks
+    // collect line of blocks
     // TODO: this needs some more work probably
-    basic.turn_to_heading(&mut jodio.dt, 0.0.deg()).await;
     jodio.intake_command.set(Command::Collect);
-    // collect blocks
-    basic.drive_distance(&mut jodio.dt, 66.301).await;
-    // face middle goal
-    basic.turn_to_heading(&mut jodio.dt, 315.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, -15.55).await;
+    let blocks = ahead(jodio, 0.0.deg(), 66.301);
+    jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, blocks, 0.0.deg(), false)
+        .await;
+    // back into middle goal
+    let middle_goal = ahead(jodio, 315.0.deg(), -15.55);
+    let result = jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, middle_goal, 315.0.deg(), true)
+        .await;
+    recover(jodio, &result);
     jodio.intake_command.set(Command::ScoreMiddle);
     // don't score too many blocks
     sleep(Duration::from_millis(250)).await; // TODO: placeholder duration
@@ -57,57 +112,105 @@ pub async fn awp(jodio: &mut Jodio) {
     // PHASE 3: Left long  goal scoring
 
     // drive to long goal
-    basic.drive_distance(&mut jodio.dt, -45.52).await;
-    basic.turn_to_heading(&mut jodio.dt, 270.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, -12.712).await;
+    jodio.motion.drive_distance(&mut jodio.dt, -45.52).await;
+    let long_goal = ahead(jodio, 270.0.deg(), -12.712);
+    let result = jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, long_goal, 270.0.deg(), true)
+        .await;
+    recover(jodio, &result);
     jodio.intake_command.set(Command::ScoreLong);
 }
 
-// TODO: refactor to work for left
-pub async fn safe(jodio: &mut Jodio, _left: bool) {
-    let mut basic = Basic {
-        linear_controller: consts::LINEAR_PID,
-        angular_controller: consts::ANGULAR_PID,
-        linear_tolerances: consts::LINEAR_TOLERANCES,
-        angular_tolerances: consts::ANGULAR_TOLERANCES,
-        // TODO: add timeout
-        timeout: None,
+/// Scores on the low goal and a long goal from the right side, or the same route mirrored across
+/// the x-axis from the left side if `left` is set.
+pub async fn safe(jodio: &mut Jodio, left: bool) {
+    let _trace = jodio
+        .trace
+        .start(if left { "left_safe" } else { "right_safe" });
+
+    // the route is written for the right side
+    let side = if left { -1.0 } else { 1.0 };
+    let point = |x: f64, y: f64| Point::new(x, y * side);
+    let heading = |degrees: f64| Angle::from_degrees(degrees * side);
+
+    let start = if left {
+        SAFE_START.mirrored()
+    } else {
+        SAFE_START
     };
-
-    let point0: Point = (-44.866, -14.0).into();
-
-    jodio.dt.tracking.set_position(point0);
-    jodio.dt.tracking.set_heading(90.0.deg());
+    let point0 = start.position;
+    place(jodio, start);
 
     jodio.intake_command.set(Command::Collect);
-    let point1: Point = (-22.374, -21.827).into();
-
-    basic.turn_to_point(&mut jodio.dt, point1).await;
-    basic
-        .drive_distance(&mut jodio.dt, point1.distance(point0))
-        .await;
-
-    let point2: Point = (-13.769, -13.612).into();
-    basic.turn_to_point(&mut jodio.dt, point2).await;
-    basic
-        .drive_distance(&mut jodio.dt, point2.distance(point1))
+    let point1 = point(-22.374, -21.827);
+    jodio.trace.waypoint(point1);
+
+    let point2 = point(-13.769, -13.612);
+    jodio.trace.waypoint(point2);
+
+    jodio.motion.turn_to_point(&mut jodio.dt, point1).await;
+    let to_goal = path::sample(
+        &[
+            PathSegment::line(
+                [point0.x, point0.y],
+                [point1.x, point1.y],
+                consts::PURSUIT_SPEED,
+            ),
+            PathSegment::line(
+                [point1.x, point1.y],
+                [point2.x, point2.y],
+                consts::PURSUIT_SPEED,
+            ),
+        ],
+        consts::PATH_SPACING,
+    );
+    let result = jodio
+        .motion
+        .follow_path(&mut jodio.dt, &to_goal, false)
         .await;
+    recover(jodio, &result);
     jodio.intake_command.set(Command::ScoreLow);
     sleep(Duration::from_millis(500)).await; // TODO: placeholder duration
     jodio.intake_command.set(Command::Collect);
 
-    let point3: Point = (-47.213, -47.056).into();
-    basic
-        .drive_distance(&mut jodio.dt, -point3.distance(point2))
+    let point3 = point(-47.213, -47.056);
+    jodio.trace.waypoint(point3);
+    let to_loader = Trajectory::generate(
+        &path::sample(
+            &[PathSegment::line(
+                [point2.x, point2.y],
+                [point3.x, point3.y],
+                1.0,
+            )],
+            consts::PATH_SPACING,
+        ),
+        Constraints {
+            track_width: jodio.config.track_width,
+            ..consts::TRAJECTORY_CONSTRAINTS
+        },
+        true,
+    );
+    let result = jodio
+        .motion
+        .follow_trajectory(&mut jodio.dt, &to_loader)
         .await;
+    recover(jodio, &result);
 
-    basic.turn_to_heading(&mut jodio.dt, 270.0.deg()).await;
     jodio.matchloader.extend();
-    basic.drive_distance(&mut jodio.dt, 6.063).await;
+    let loader = ahead(jodio, heading(270.0), 6.063);
+    jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, loader, heading(270.0), false)
+        .await;
+    jodio.relocalizer.relocalize(&mut jodio.dt.tracking);
 
     jodio.matchloader.retract();
-    basic.turn_to_heading(&mut jodio.dt, 90.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, 23.079).await;
+    let long_goal = ahead(jodio, heading(90.0), 23.079);
+    jodio
+        .motion
+        .move_to_pose(&mut jodio.dt, long_goal, heading(90.0), false)
+        .await;
     jodio.intake_command.set(Command::ScoreLong);
 }
 
@@ -118,72 +221,3 @@ pub async fn right_safe(jodio: &mut Jodio) {
 pub async fn left_safe(jodio: &mut Jodio) {
     safe(jodio, true).await;
 }
-
-pub async fn skills(jodio: &mut Jodio) {
-    // disable color sorting
-    jodio.allegiance.set(None);
-
-    let point0: Point = (-61.0, 18.5).into();
-    jodio.dt.tracking.set_position(point0);
-    jodio.dt.tracking.set_heading(90.0.deg());
-
-    let mut basic = Basic {
-        linear_controller: consts::LINEAR_PID,
-        angular_controller: consts::ANGULAR_PID,
-        linear_tolerances: consts::LINEAR_TOLERANCES,
-        angular_tolerances: consts::ANGULAR_TOLERANCES,
-        // TODO: add timeout
-        timeout: None,
-    };
-
-    // collect blocks
-    jodio.intake_command.set(Command::Collect);
-    let point1: Point = (-22.801, 22.432).into();
-    basic
-        .drive_distance_at_heading(&mut jodio.dt, point1.distance(point0), 316.917.deg())
-        .await;
-
-    // score on middle goal
-    basic.turn_to_heading(&mut jodio.dt, 135.427.deg()).await;
-    basic.drive_distance(&mut jodio.dt, -13.92436).await;
-    jodio.intake_command.set(Command::ScoreMiddle);
-    sleep(Duration::from_millis(500)).await; // TODO: placeholder
-
-    // collect loader
-    basic.drive_distance(&mut jodio.dt, -34.7969).await;
-
-    basic.turn_to_heading(&mut jodio.dt, 316.918.deg()).await;
-    jodio.matchloader.extend();
-    basic.drive_distance(&mut jodio.dt, 7.793).await;
-    sleep(consts::MATCHLOADER_CLEAR_TIME).await;
-    jodio.matchloader.retract();
-
-    // score on long goal
-    basic.turn_to_heading(&mut jodio.dt, 90.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, -23.95).await;
-    jodio.intake_command.set(Command::ScoreLong);
-    sleep(Duration::from_millis(1000)).await;
-    jodio.intake_command.set(Command::Collect);
-
-    // go to blue side
-    basic.drive_distance(&mut jodio.dt, 8.175).await;
-    basic.turn_to_heading(&mut jodio.dt, 0.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, 9.918).await;
-    basic.turn_to_heading(&mut jodio.dt, 90.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, 84.92).await;
-
-    // collect loader
-    basic.turn_to_heading(&mut jodio.dt, 180.0.deg()).await;
-    basic.drive_distance(&mut jodio.dt, 9.918).await;
-    basic.turn_to_heading(&mut jodio.dt, 90.0.deg()).await;
-    jodio.matchloader.extend();
-    basic.drive_distance(&mut jodio.dt, 10.68).await;
-    sleep(consts::MATCHLOADER_CLEAR_TIME).await;
-    jodio.matchloader.retract();
-
-    // score on long goal
-    basic.drive_distance(&mut jodio.dt, 25.053).await;
-    jodio.intake_command.set(Command::ScoreLong);
-    sleep(Duration::from_millis(1000)).await;
-    jodio.intake_command.set(Command::Collect);
-}
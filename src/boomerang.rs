@@ -0,0 +1,135 @@
+use std::{
+    f64::consts::PI,
+    time::{Duration, Instant},
+};
+
+use evian::{
+    control::loops::{AngularPid, Feedback, Pid},
+    drivetrain::model::Arcade,
+    math::{Angle, Vec2},
+    prelude::*,
+};
+use log::warn;
+use vexide::time::sleep;
+
+use crate::{ekf::wrap, steering::turn_power};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Boomerang
+///
+/// Drives to a point and arrives facing a heading in one motion. Instead of aiming at the target
+/// itself, the robot aims at a "carrot" point behind the target along the target heading. The
+/// carrot starts [`Boomerang::lead`] times the remaining distance out and closes in on the target
+/// as the robot does, so the robot swings around and comes in straight along the heading.
+///
+/// Within [`Boomerang::settle_radius`] of the target the robot turns to the target heading
+/// directly, since the carrot is too close to steer towards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Boomerang {
+    /// Controller for the distance left to the target.
+    pub linear_controller: Pid,
+
+    /// Controller for the heading.
+    pub angular_controller: AngularPid,
+
+    pub linear_tolerances: Tolerances,
+    pub angular_tolerances: Tolerances,
+
+    /// How far behind the target the carrot starts, as a fraction of the distance to the target.
+    /// Zero drives straight at the target, and larger values swing out wider.
+    pub lead: f64,
+
+    /// Distance from the target at which the robot stops chasing the carrot.
+    pub settle_radius: f64,
+
+    /// Largest linear power, between 0 and 1.
+    pub max_speed: f64,
+
+    /// How long the motion can take before giving up.
+    pub timeout: Option<Duration>,
+}
+
+impl Boomerang {
+    /// Drives to `point` and ends facing `heading`. If `reverse` is set the robot backs into the
+    /// pose instead.
+    pub async fn move_to_pose<M: Arcade, T: TracksPosition + TracksHeading + TracksVelocity>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        point: Vec2<f64>,
+        heading: Angle,
+        reverse: bool,
+    ) {
+        let mut linear_controller = self.linear_controller;
+        let mut angular_controller = self.angular_controller;
+        let mut linear_tolerances = self.linear_tolerances;
+        let mut angular_tolerances = self.angular_tolerances;
+
+        // backing in is driving forwards into the pose with the robot turned around
+        let turn_around = Angle::from_radians(if reverse { PI } else { 0.0 });
+        let target_heading = heading + turn_around;
+
+        let start_time = Instant::now();
+        let mut prev_time = start_time;
+
+        loop {
+            sleep(UPDATE_INTERVAL).await;
+            let now = Instant::now();
+            let dt = now - prev_time;
+            prev_time = now;
+
+            let position = drivetrain.tracking.position();
+            let robot_heading = drivetrain.tracking.heading() + turn_around;
+            let distance = position.distance(point);
+            let heading_error = wrap(target_heading.as_radians() - robot_heading.as_radians());
+
+            if linear_tolerances.check(distance, drivetrain.tracking.linear_velocity())
+                && angular_tolerances.check(heading_error, drivetrain.tracking.angular_velocity())
+            {
+                break;
+            }
+            if self
+                .timeout
+                .is_some_and(|timeout| start_time.elapsed() > timeout)
+            {
+                warn!("move to pose timed out {distance:.1} in from the target");
+                break;
+            }
+
+            let (sin, cos) = target_heading.as_radians().sin_cos();
+            let carrot = Vec2::new(
+                point.x - cos * self.lead * distance,
+                point.y - sin * self.lead * distance,
+            );
+
+            let settling = distance < self.settle_radius;
+            let aim = if settling {
+                target_heading
+            } else {
+                Angle::from_radians((carrot.y - position.y).atan2(carrot.x - position.x))
+            };
+
+            // only the part of the remaining distance along the robot's heading is driven, so the
+            // robot slows down as it passes the target instead of circling back to it
+            let (sin, cos) = robot_heading.as_radians().sin_cos();
+            let along = (point.x - position.x) * cos + (point.y - position.y) * sin;
+            let mut linear = linear_controller
+                .update(0.0, along, dt)
+                .clamp(-self.max_speed, self.max_speed);
+            if !settling {
+                // turn towards the carrot before driving at it
+                linear *= wrap(aim.as_radians() - robot_heading.as_radians())
+                    .cos()
+                    .max(0.0);
+            }
+
+            let angular = turn_power(angular_controller.update(robot_heading, aim, dt));
+
+            _ = drivetrain
+                .model
+                .drive_arcade(if reverse { -linear } else { linear }, angular);
+        }
+
+        _ = drivetrain.model.drive_arcade(0.0, 0.0);
+    }
+}
@@ -0,0 +1,201 @@
+use std::{f64::consts::TAU, time::Duration};
+
+use evian::prelude::*;
+use log::{error, info, warn};
+use vexide::prelude::*;
+
+use crate::{Jodio, consts, robot_config::RobotConfig, tracking_config::OdometrySource};
+
+/// How long the robot is given to stop moving before the final readings are taken.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Fits the effective wheel diameters by driving a measured distance.
+///
+/// Line the robot up on a tile seam and press A, then drive (or push) it straight forward
+/// [`consts::CALIBRATION_DISTANCE`] inches and press A again. The drive wheel diameter is always
+/// fitted, and the tracking wheel diameter is fitted too if odometry is using dedicated tracking
+/// wheels. The results are saved to the robot config and used after the program restarts.
+///
+/// This is started from driver control by holding down and pressing left, since VEXos ignores the
+/// controller during autonomous.
+pub async fn wheel_diameter(jodio: &mut Jodio) {
+    info!(
+        "calibrating wheel diameter: press A, drive {} in straight forward, then press A again",
+        consts::CALIBRATION_DISTANCE
+    );
+
+    drive_until_a(jodio).await;
+    let drive_start = drive_travel(jodio);
+    let tracking_start = jodio.dt.tracking.forward_travel();
+    info!("started, press A once the robot has been driven the full distance");
+
+    drive_until_a(jodio).await;
+    settle(jodio).await;
+    let (Some(drive_start), Some(drive_end)) = (drive_start, drive_travel(jodio)) else {
+        error!("couldn't read the drive motors, wheel diameter wasn't calibrated");
+        return;
+    };
+    let tracking_travel = jodio.dt.tracking.forward_travel() - tracking_start;
+
+    let Some(wheel_diameter) = fit_diameter(jodio.config.wheel_diameter, drive_end - drive_start)
+    else {
+        return;
+    };
+    info!("drive wheel diameter: {wheel_diameter:.4} in");
+
+    let tracking_wheel_diameter = if jodio.odometry == OdometrySource::TrackingWheels {
+        let Some(diameter) = fit_diameter(jodio.config.tracking_wheel_diameter, tracking_travel)
+        else {
+            return;
+        };
+        info!("tracking wheel diameter: {diameter:.4} in");
+        Some(diameter)
+    } else {
+        None
+    };
+
+    save(jodio, |config| {
+        config.wheel_diameter = wheel_diameter;
+        if let Some(diameter) = tracking_wheel_diameter {
+            config.tracking_wheel_diameter = diameter;
+        }
+    });
+}
+
+/// Fits the effective track width by spinning in place against the IMU.
+///
+/// Put the robot somewhere it can spin freely and press A. The robot spins
+/// [`consts::CALIBRATION_TURNS`] full turns, and the track width that makes the drive wheels agree
+/// with the IMU is saved to the robot config and used after the program restarts. The track width
+/// is fitted from the drive wheel travel, so calibrate the wheel diameter and restart first.
+///
+/// This is started from driver control by holding down and pressing right.
+pub async fn track_width(jodio: &mut Jodio) {
+    if jodio.calibration_imu.is_none() {
+        error!("the imu isn't calibrated, track width can't be calibrated without it");
+        return;
+    }
+
+    info!(
+        "calibrating track width: press A to spin {} turns in place",
+        consts::CALIBRATION_TURNS
+    );
+    drive_until_a(jodio).await;
+
+    let (Some(start_rotation), (Some(start_left), Some(start_right))) =
+        (imu_rotation(jodio), jodio.drive_motors.side_positions())
+    else {
+        error!("couldn't read the imu or drive motors, track width wasn't calibrated");
+        return;
+    };
+
+    let target = f64::from(consts::CALIBRATION_TURNS) * TAU;
+    loop {
+        match imu_rotation(jodio) {
+            Some(rotation) if (rotation - start_rotation).abs() >= target => break,
+            Some(_) => {}
+            None => {
+                warn!("lost the imu, track width calibration stopped");
+                let _ = jodio.drive_motors.brake(BrakeMode::Brake);
+                return;
+            }
+        }
+
+        jodio
+            .dt
+            .model
+            .drive_arcade(0.0, consts::CALIBRATION_TURN_POWER)
+            .unwrap_or_else(|e| warn!("couldn't drive drivetrain: {e}"));
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    settle(jodio).await;
+    let (Some(end_rotation), (Some(end_left), Some(end_right))) =
+        (imu_rotation(jodio), jodio.drive_motors.side_positions())
+    else {
+        error!("couldn't read the imu or drive motors, track width wasn't calibrated");
+        return;
+    };
+
+    // each wheel travels along a circle with a radius of half the track width, in opposite
+    // directions
+    let travel = (end_left - start_left).abs() + (end_right - start_right).abs();
+    let track_width = travel / (end_rotation - start_rotation).abs();
+
+    info!("track width: {track_width:.4} in");
+
+    save(jodio, |config| config.track_width = track_width);
+}
+
+/// Lets the driver drive at reduced power until A is pressed.
+async fn drive_until_a(jodio: &mut Jodio) {
+    loop {
+        let state = jodio.ctrl.state().unwrap_or_default();
+        if state.button_a.is_now_pressed() {
+            break;
+        }
+
+        let scale = consts::CALIBRATION_DRIVE_SCALE;
+        jodio
+            .dt
+            .model
+            .drive_arcade(state.left_stick.y() * scale, state.right_stick.x() * scale)
+            .unwrap_or_else(|e| warn!("couldn't drive drivetrain: {e}"));
+        sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Stops the drivetrain and waits for it to come to rest.
+async fn settle(jodio: &mut Jodio) {
+    jodio
+        .drive_motors
+        .brake(BrakeMode::Brake)
+        .unwrap_or_else(|e| warn!("couldn't brake drivetrain: {e}"));
+    sleep(SETTLE_TIME).await;
+}
+
+/// Total rotation the IMU has measured in radians.
+fn imu_rotation(jodio: &Jodio) -> Option<f64> {
+    let imu = jodio.calibration_imu.as_ref()?;
+    imu.rotation().ok().map(|rotation| rotation.as_radians())
+}
+
+/// Distance the drive wheels have travelled forward, averaged between both sides.
+fn drive_travel(jodio: &Jodio) -> Option<f64> {
+    match jodio.drive_motors.side_positions() {
+        (Some(left), Some(right)) => Some((left + right) / 2.0),
+        (side, None) | (None, side) => side,
+    }
+}
+
+/// Scales `diameter` so that `measured` inches of travel would have read as the calibration
+/// distance.
+fn fit_diameter(diameter: f64, measured: f64) -> Option<f64> {
+    // anything this far off is a reversed sensor or a missed button press, not a bad measurement
+    if measured < consts::CALIBRATION_DISTANCE / 2.0 {
+        error!(
+            "only measured {measured:.2} in of the {} in driven, check that the robot was driven \
+             forward and that every sensor is reading in the right direction",
+            consts::CALIBRATION_DISTANCE
+        );
+        return None;
+    }
+
+    Some(diameter * consts::CALIBRATION_DISTANCE / measured)
+}
+
+/// Updates the measurements saved on the SD card.
+fn save(jodio: &Jodio, update: impl FnOnce(&mut RobotConfig)) {
+    // start from what's saved rather than what the robot booted with, so that calibrations run
+    // since boot aren't undone
+    let mut config = RobotConfig::load(consts::ROBOT_CONFIG_PATH, jodio.config);
+    update(&mut config);
+
+    match config.save(consts::ROBOT_CONFIG_PATH) {
+        Ok(()) => info!(
+            "saved to {}, restart the program to use the new measurements",
+            consts::ROBOT_CONFIG_PATH
+        ),
+        Err(e) => error!("couldn't save to {}: {e}", consts::ROBOT_CONFIG_PATH),
+    }
+}
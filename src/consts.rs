@@ -2,8 +2,25 @@ use std::time::Duration;
 
 use evian::{
     control::loops::{AngularPid, Pid},
+    math::Angle,
     prelude::Tolerances,
 };
+use vexide::prelude::{Direction, Gearset};
+
+use crate::{
+    drive_config::{DriveConfig, MotorConfig},
+    ekf,
+    imu::ImuAxis,
+    path,
+    ports::Ports,
+    profile::Limits,
+    profiled::Feedforward,
+    pure_pursuit::PurePursuit,
+    ramsete::Ramsete,
+    relocalize::DistanceMount,
+    tracking_config::TrackingWheelConfig,
+    trajectory::{self, Constraints},
+};
 
 // Curvature Drive
 pub const TURN_NONLINEARITY: f64 = 0.65;
@@ -12,11 +29,110 @@ pub const DEADZONE: f64 = 4.0 / 100.0;
 pub const SLEW: f64 = 0.3;
 pub const NEGATIVE_INERTIA_SCALAR: f64 = 4.0;
 
+// Heading Hold
+// TODO: Tune
+pub const HEADING_HOLD_PID: AngularPid = AngularPid::new(0.5, 0.0, 0.0, None);
+
+// Field-Centric Drive
+// TODO: Tune
+pub const FIELD_CENTRIC_PID: AngularPid = AngularPid::new(1.0, 0.0, 0.0, None);
+// field heading the drivers face from the driver station
+pub const DRIVER_FORWARD: Angle = Angle::from_degrees(0.0);
+
+// Hold-Position Defense
+// TODO: Tune
+pub const DEFENSE_LINEAR_PID: Pid = Pid::new(0.1, 0.0, 0.0, None);
+pub const DEFENSE_ANGULAR_PID: AngularPid = AngularPid::new(1.0, 0.0, 0.0, None);
+
+// Drivetrain
+// nominal measurements, the calibration routines fit the real ones (see ROBOT_CONFIG_PATH)
+pub const DRIVE: DriveConfig<1> = DriveConfig::new(
+    [MotorConfig::new(3, Gearset::Green, Direction::Reverse)],
+    [MotorConfig::new(4, Gearset::Green, Direction::Reverse)],
+    3.25,
+    36.0 / 48.0,
+    trajectory::TRACK_WIDTH,
+);
+
+// Traction Control
+// TODO: Tune
+pub const MAX_ACCELERATION: f64 = 4.0;
+pub const SLIP_THRESHOLD: f64 = 12.0;
+pub const SLIP_POWER_SCALE: f64 = 0.5;
+// which of the IMU's axes points towards the front of the robot
+pub const IMU_FORWARD_AXIS: ImuAxis = ImuAxis::X;
+
+// Tip Guard
+// TODO: Tune
+pub const TIP_LEAN_ANGLE: Angle = Angle::from_degrees(12.0);
+pub const TIP_ANGLE: Angle = Angle::from_degrees(25.0);
+pub const TIP_LEAN_POWER_SCALE: f64 = 0.5;
+pub const TIP_RECOVERY_POWER: f64 = 0.4;
+
 // PID
 // TODO: Tune
 pub const LINEAR_PID: Pid = Pid::new(0.0, 0.0, 0.0, None);
 pub const ANGULAR_PID: AngularPid = AngularPid::new(0.0, 0.0, 0.0, None);
 
+// Motion Profiling
+// TODO: Tune
+pub const DRIVE_LIMITS: Limits = Limits {
+    // about 25.5 in/s free speed
+    max_velocity: 22.0,
+    max_acceleration: 60.0,
+    max_jerk: Some(400.0),
+};
+pub const TURN_LIMITS: Limits = Limits {
+    // about 4.4 rad/s free speed
+    max_velocity: 3.5,
+    max_acceleration: 12.0,
+    max_jerk: Some(80.0),
+};
+pub const LINEAR_FEEDFORWARD: Feedforward = Feedforward {
+    ks: 0.05,
+    kv: 0.04,
+    ka: 0.004,
+};
+pub const ANGULAR_FEEDFORWARD: Feedforward = Feedforward {
+    ks: 0.05,
+    kv: 0.23,
+    ka: 0.02,
+};
+
+// Pure Pursuit
+// TODO: Tune
+// track width is replaced with the calibrated one, and timeout with MOTION_TIMEOUT, when following
+pub const PURE_PURSUIT: PurePursuit = PurePursuit {
+    min_lookahead: 8.0,
+    max_lookahead: 18.0,
+    lookahead_time: 0.4,
+    track_width: DRIVE.track_width,
+    slowdown_distance: 12.0,
+    min_speed: 0.2,
+    end_tolerance: 1.0,
+    timeout: None,
+};
+pub const PATH_SPACING: f64 = path::SPACING;
+pub const PURSUIT_SPEED: f64 = 0.6;
+
+// Boomerang
+// TODO: Tune
+pub const BOOMERANG_LEAD: f64 = 0.6;
+pub const BOOMERANG_SETTLE_RADIUS: f64 = 6.0;
+
+// RAMSETE
+// TODO: Tune
+// shared with host/src/bin/trajectory-gen.rs so generated trajectories match the robot
+pub const TRAJECTORY_CONSTRAINTS: Constraints = trajectory::CONSTRAINTS;
+// track width is replaced with the calibrated one when following
+pub const RAMSETE: Ramsete = Ramsete {
+    b: 0.0013,
+    zeta: 0.7,
+    track_width: DRIVE.track_width,
+    // about 25.5 in/s free speed
+    velocity_power: 0.04,
+};
+
 // Tolerances
 pub const LINEAR_TOLERANCES: Tolerances = Tolerances::new()
     .error(1.0)
@@ -27,11 +143,82 @@ pub const ANGULAR_TOLERANCES: Tolerances = Tolerances::new()
     .velocity(0.05)
     .duration(Duration::from_millis(15));
 
+// Motion
+// TODO: Tune
+pub const MOTION_TIMEOUT: Duration = Duration::from_secs(3);
+pub const MOTION_SPEED: f64 = 1.0;
+pub const MOTION_TURN_SPEED: f64 = 1.0;
+pub const STALL_SPEED: f64 = 1.0;
+pub const STALL_TIME: Duration = Duration::from_millis(400);
+
 // Tracking
+pub const IMU_PORT: u8 = 10;
 // TODO: replace placeholders
 pub const PARA_WHEEL_OFFSET: f64 = 0.5;
 pub const PERP_WHEEL_OFFSET: f64 = 0.5;
 pub const DISTANCE_SENSOR_OFFSET: f64 = 0.5;
+pub const TRACKING_WHEEL_DIAMETER: f64 = 2.0;
+// no tracking wheels are fitted yet, so odometry uses the drive wheels
+pub const PARALLEL_WHEELS: &[TrackingWheelConfig] = &[];
+pub const PERPENDICULAR_WHEEL: Option<TrackingWheelConfig> = None;
+
+// GPS Fusion
+// None until the gps is fitted, which leaves tracking on odometry alone
+pub const GPS_PORT: Option<u8> = None;
+// TODO: replace placeholders
+// offset of the gps from the center of rotation in meters
+pub const GPS_OFFSET: [f64; 2] = [0.0, 0.0];
+pub const GPS_ROTATION: Angle = Angle::from_degrees(0.0);
+pub const GPS_MAX_ERROR: f64 = 2.0;
+pub const GPS_MAX_SPEED: f64 = 30.0;
+pub const GPS_HEADING_ERROR: Angle = Angle::from_degrees(2.0);
+// tuned against the simulation in ekf.rs's tests
+pub const ODOMETRY_LINEAR_NOISE: f64 = ekf::LINEAR_NOISE;
+pub const ODOMETRY_ANGULAR_NOISE: f64 = ekf::ANGULAR_NOISE;
+pub const GPS_GATE: f64 = ekf::GATE;
+
+// Relocalization
+// facing backwards and to the left, None until they're fitted. Without any, relocalization and
+// the particle filter are off and start poses can't be checked
+pub const DISTANCE_SENSORS: [Option<DistanceMount>; 2] = [None, None];
+pub const RELOCALIZE_SQUARE_TOLERANCE: Angle = Angle::from_degrees(5.0);
+pub const RELOCALIZE_MAX_CORRECTION: f64 = 6.0;
+// how far each distance sensor can be from its expected reading when the robot is placed
+pub const START_TOLERANCE: f64 = 1.0;
+
+// Particle Filter
+// TODO: Tune
+pub const MCL_PARTICLES: usize = 250;
+pub const MCL_SENSOR_NOISE: f64 = 1.0;
+pub const MCL_START_SPREAD: f64 = 1.0;
+pub const MCL_MAX_SPREAD: f64 = 1.5;
+pub const MCL_SEED: u64 = 0x5eed;
+
+// Motion Monitor
+// TODO: Tune
+pub const MONITOR_SLIP_THRESHOLD: f64 = 12.0;
+pub const MONITOR_PUSH_THRESHOLD: f64 = 8.0;
+pub const MONITOR_PUSH_TURN_THRESHOLD: f64 = 1.0;
+pub const MONITOR_COLLISION_ACCELERATION: f64 = 1.5;
+// uncertainty added to the tracked pose after a disturbance during autonomous
+pub const DISTURBANCE_POSITION_ERROR: f64 = 3.0;
+pub const DISTURBANCE_HEADING_ERROR: Angle = Angle::from_degrees(5.0);
+
+// Tracing
+pub const TRACE_INTERVAL: Duration = Duration::from_millis(20);
+
+// Calibration
+// measurements fitted by the calibration routines are saved here on the SD card
+pub const ROBOT_CONFIG_PATH: &str = "robot.cfg";
+// two tiles
+pub const CALIBRATION_DISTANCE: f64 = 48.0;
+pub const CALIBRATION_TURNS: u8 = 5;
+pub const CALIBRATION_TURN_POWER: f64 = 0.4;
+pub const CALIBRATION_DRIVE_SCALE: f64 = 0.4;
+
+// Routes
+// route files here on the SD card are added to the selector next to the ones in routes/
+pub const SD_ROUTES_PATH: &str = "routes";
 
 // Intake
 pub const BLOCK_PROXIMITY_THRESHOLD: f64 = 0.5;
@@ -44,3 +231,35 @@ pub const BLUE_HUE: f64 = -120.0;
 
 pub const MATCHLOADER_CLEAR_TIME: Duration = Duration::from_millis(500);
 pub const HALF_MATCHLOADER_CLEAR_TIME: Duration = Duration::from_millis(500);
+
+// Ports
+// every smart port on the robot, checked together at compile time so that two devices can't
+// share one
+const _: Ports = {
+    // the intake's motors and optical sensor, which `main` takes from `peris`
+    let mut ports = Ports::new().with_all(&[2, 1, 11, 21]);
+    ports = DRIVE.ports(ports).with(IMU_PORT);
+
+    let mut i = 0;
+    while i < PARALLEL_WHEELS.len() {
+        ports = PARALLEL_WHEELS[i].ports(ports);
+        i += 1;
+    }
+    if let Some(wheel) = PERPENDICULAR_WHEEL {
+        ports = wheel.ports(ports);
+    }
+
+    if let Some(port) = GPS_PORT {
+        ports = ports.with(port);
+    }
+
+    let mut i = 0;
+    while i < DISTANCE_SENSORS.len() {
+        if let Some(mount) = DISTANCE_SENSORS[i] {
+            ports = ports.with(mount.port);
+        }
+        i += 1;
+    }
+
+    ports
+};
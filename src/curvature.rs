@@ -1,17 +1,12 @@
 use std::f64::consts::FRAC_PI_2;
 
-use evian::{
-    drivetrain::{model::Arcade, Drivetrain},
-    tracking::Tracking,
-};
-
 /// Curvature Drive (aka Cheesy Drive) Controller
 ///
 /// Curvature Drive is a nonlinear and curvature-based drivetrain control algorithm. Optimized for
 /// driver intuition and precise handling, it smooths inputs and adapts to the situation. Unlike
 /// other algorithms such as Arcade Drive and Tank Drive, it performs some mathematical computations
 /// and accepts some constants and maintains an internal state that changes every time the algorithm
-/// is ran using [`CurvatureDrive::update`].
+/// is ran using [`CurvatureDrive::arcade`].
 ///
 /// This implemenation is based on <https://wiki.purduesigbots.com/software/robotics-basics/curvature-cheesy-drive>.
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -74,29 +69,11 @@ impl CurvatureDrive {
         }
     }
 
-    /// Runs the Curvature Drive algorithm, updates the internal state, and powers the drivetrain.
-    ///
-    /// # Examples
-    /// ```
-    /// struct Robot {
-    ///     controller: Controller,
-    ///     drivetrain: Drivetrain<Differential, ()>,
-    ///     curvature_drive: CurvatureDrive,
-    /// }
+    /// Runs the Curvature Drive algorithm and updates the internal state.
     ///
-    /// let state = self.controller.state().unwrap();
-    /// self.curvature.update(
-    ///     &mut self.drivetrain,
-    ///     state.left_stick.y(),
-    ///     state.right_stick.x(),
-    /// ).expect("couldn't set drivetrain voltages");
-    /// ```
-    pub fn update<M: Arcade>(
-        &mut self,
-        drivetrain: &mut Drivetrain<M, impl Tracking>,
-        throttle: f64,
-        turn: f64,
-    ) -> Result<(), M::Error> {
+    /// Returns the `(linear, angular)` arcade powers rather than powering the drivetrain, so that
+    /// other driver assists can adjust them before they are sent to it.
+    pub fn arcade(&mut self, throttle: f64, turn: f64) -> (f64, f64) {
         let mut turn_in_place = false;
         let mut linear_power = throttle;
 
@@ -116,7 +93,7 @@ impl CurvatureDrive {
         // turn is remapped using a sine function whose waviness is determined by turn nonlinearity
         let remapped_turn = self.remap_turn(turn);
 
-        let (linear_power, angular_power) = if turn_in_place {
+        let powers = if turn_in_place {
             // square function
             (0.0, remapped_turn * remapped_turn.abs())
         } else {
@@ -136,7 +113,7 @@ impl CurvatureDrive {
         self.prev_turn = turn;
         self.prev_throttle = throttle;
 
-        drivetrain.model.drive_arcade(linear_power, angular_power)
+        powers
     }
 
     fn remap_turn(&self, turn: f64) -> f64 {
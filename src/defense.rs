@@ -0,0 +1,96 @@
+use std::time::Instant;
+
+use evian::{
+    control::loops::{AngularPid, Feedback, Pid},
+    math::{Angle, Vec2},
+};
+
+use crate::steering::turn_power;
+
+/// Hold-Position Defense
+///
+/// Locks the robot's current odometry pose and actively drives back to it when pushed. Braking
+/// the motors in [`BrakeMode::Hold`] only resists a push until the motors lose the fight, after
+/// which the robot stays wherever it was shoved. Defense instead keeps correcting towards the
+/// locked pose, so a robot that gets pushed off a goal will drive back onto it.
+///
+/// A differential drivetrain can't move sideways, so only the part of the displacement along the
+/// robot's heading is corrected. Sideways pushes are still resisted by the wheels' grip.
+///
+/// [`BrakeMode::Hold`]: vexide::smart::motor::BrakeMode::Hold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Defense {
+    /// Controller used to drive back to the locked position.
+    pub linear_controller: Pid,
+
+    /// Controller used to turn back to the locked heading.
+    pub angular_controller: AngularPid,
+
+    lock: Option<Lock>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Lock {
+    position: Vec2<f64>,
+    heading: Angle,
+    linear_controller: Pid,
+    angular_controller: AngularPid,
+    prev_time: Instant,
+}
+
+impl Defense {
+    /// Constructs a disengaged [`Defense`] with the provided controllers.
+    pub fn new(linear_controller: Pid, angular_controller: AngularPid) -> Self {
+        Self {
+            linear_controller,
+            angular_controller,
+            lock: None,
+        }
+    }
+
+    /// Whether a pose is currently locked.
+    pub fn is_engaged(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Locks the given pose as the one to defend.
+    pub fn engage(&mut self, position: Vec2<f64>, heading: Angle) {
+        self.lock = Some(Lock {
+            position,
+            heading,
+            linear_controller: self.linear_controller,
+            angular_controller: self.angular_controller,
+            prev_time: Instant::now(),
+        });
+    }
+
+    /// Forgets the locked pose, if any.
+    pub fn disengage(&mut self) {
+        self.lock = None;
+    }
+
+    /// Computes the `(linear, angular)` arcade powers needed to return to the locked pose.
+    ///
+    /// The sign of the angular power matches a turn stick (positive turns clockwise). Returns
+    /// `None` while no pose is locked.
+    pub fn update(&mut self, position: Vec2<f64>, heading: Angle) -> Option<(f64, f64)> {
+        let lock = self.lock.as_mut()?;
+
+        let now = Instant::now();
+        let dt = now - lock.prev_time;
+        lock.prev_time = now;
+
+        if dt.is_zero() {
+            return Some((0.0, 0.0));
+        }
+
+        // project the displacement onto our heading, since we can only drive forwards or backwards
+        let displacement = lock.position - position;
+        let error = displacement.x * heading.cos() + displacement.y * heading.sin();
+
+        let linear = lock.linear_controller.update(0.0, error, dt);
+        let angular = turn_power(lock.angular_controller.update(heading, lock.heading, dt));
+
+        Some((linear, angular))
+    }
+}
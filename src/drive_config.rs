@@ -0,0 +1,247 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use evian::{drivetrain::model::Differential, tracking::wheeled::TrackingWheel};
+use log::error;
+use vexide::{
+    display::{Font, FontFamily, FontSize, Text},
+    prelude::*,
+    smart::SmartPort,
+};
+
+use crate::{
+    drive_motors::{DriveMotors, SharedMotors},
+    ports::{MAX_PORT, Ports},
+};
+
+/// Maximum number of motors on one side of the drivetrain.
+pub const MAX_SIDE_MOTORS: usize = 4;
+
+/// How long the disconnected motor warning stays on screen before boot continues.
+const WARNING_DISPLAY_TIME: Duration = Duration::from_secs(3);
+
+/// A single drive motor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MotorConfig {
+    /// Smart port number the motor is plugged into, from 1 to 21.
+    pub port: u8,
+    pub gearset: Gearset,
+    pub direction: Direction,
+}
+
+impl MotorConfig {
+    pub const fn new(port: u8, gearset: Gearset, direction: Direction) -> Self {
+        Self {
+            port,
+            gearset,
+            direction,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The port may not be used anywhere else in the program.
+    unsafe fn motor(self) -> Motor {
+        Motor::new(
+            unsafe { SmartPort::new(self.port) },
+            self.gearset,
+            self.direction,
+        )
+    }
+}
+
+/// Drivetrain Description
+///
+/// Lists every motor on the drivetrain along with the physical measurements of the robot, so that
+/// the drivetrain model, the tracking wheels, and [`DriveMotors`] can all be built from the same
+/// motors. Each side has `N` motors, where `N` can be anywhere from 1 to [`MAX_SIDE_MOTORS`].
+///
+/// Port numbers are checked when the description is constructed, so a description in a `const`
+/// with an out-of-range or duplicate port won't compile. [`DriveConfig::ports`] adds them to the
+/// robot's other ports so that they're checked against those too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DriveConfig<const N: usize> {
+    pub left: [MotorConfig; N],
+    pub right: [MotorConfig; N],
+
+    /// Diameter of the drive wheels.
+    pub wheel_diameter: f64,
+
+    /// External gearing between the motors and the drive wheels.
+    pub gearing: f64,
+
+    /// Distance between the centers of the left and right wheels.
+    pub track_width: f64,
+}
+
+/// The drivetrain built from a [`DriveConfig`].
+pub struct Drive<const N: usize> {
+    pub model: Differential,
+    pub tracking_wheels: [TrackingWheel<Rc<RefCell<[Motor; N]>>>; 2],
+    pub motors: DriveMotors,
+
+    /// Ports of the motors that weren't plugged in when the drivetrain was built.
+    pub disconnected: Vec<u8>,
+}
+
+impl<const N: usize> DriveConfig<N> {
+    /// Describes a drivetrain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a side doesn't have 1 to [`MAX_SIDE_MOTORS`] motors, or if a port is out of range
+    /// or is used by more than one motor.
+    pub const fn new(
+        left: [MotorConfig; N],
+        right: [MotorConfig; N],
+        wheel_diameter: f64,
+        gearing: f64,
+        track_width: f64,
+    ) -> Self {
+        const {
+            assert!(
+                N >= 1 && N <= MAX_SIDE_MOTORS,
+                "each side of the drivetrain needs 1 to 4 motors"
+            );
+        }
+
+        let mut i = 0;
+        while i < 2 * N {
+            let port = Self::port_at(&left, &right, i);
+            assert!(
+                port >= 1 && port <= MAX_PORT,
+                "drive motor port out of range"
+            );
+
+            let mut j = i + 1;
+            while j < 2 * N {
+                assert!(
+                    port != Self::port_at(&left, &right, j),
+                    "drive motor port used more than once"
+                );
+                j += 1;
+            }
+            i += 1;
+        }
+
+        Self {
+            left,
+            right,
+            wheel_diameter,
+            gearing,
+            track_width,
+        }
+    }
+
+    /// Adds every motor's port to `ports`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a port is already in `ports`.
+    pub const fn ports(&self, mut ports: Ports) -> Ports {
+        let mut i = 0;
+        while i < 2 * N {
+            ports = ports.with(Self::port_at(&self.left, &self.right, i));
+            i += 1;
+        }
+        ports
+    }
+
+    const fn port_at(left: &[MotorConfig; N], right: &[MotorConfig; N], index: usize) -> u8 {
+        if index < N {
+            left[index].port
+        } else {
+            right[index - N].port
+        }
+    }
+
+    /// Builds the drivetrain model, tracking wheels, and motor handles from this description.
+    ///
+    /// Every motor is checked at boot, and any that aren't plugged in are logged and listed in
+    /// [`Drive::disconnected`].
+    ///
+    /// # Safety
+    ///
+    /// None of the ports in this description may be used anywhere else in the program.
+    pub unsafe fn build(&self) -> Drive<N> {
+        let left = Rc::new(RefCell::new(
+            self.left.map(|config| unsafe { config.motor() }),
+        ));
+        let right = Rc::new(RefCell::new(
+            self.right.map(|config| unsafe { config.motor() }),
+        ));
+
+        let mut disconnected = Vec::new();
+        for (config, motor) in self
+            .left
+            .iter()
+            .zip(left.borrow().iter())
+            .chain(self.right.iter().zip(right.borrow().iter()))
+        {
+            if !motor.is_connected() {
+                error!("drive motor on port {} isn't connected", config.port);
+                disconnected.push(config.port);
+            }
+        }
+
+        Drive {
+            model: Differential::from_shared(left.clone(), right.clone()),
+            tracking_wheels: [
+                TrackingWheel::new(
+                    left.clone(),
+                    self.wheel_diameter,
+                    -self.track_width / 2.0,
+                    Some(self.gearing),
+                ),
+                TrackingWheel::new(
+                    right.clone(),
+                    self.wheel_diameter,
+                    self.track_width / 2.0,
+                    Some(self.gearing),
+                ),
+            ],
+            motors: DriveMotors::new(
+                left as SharedMotors,
+                right as SharedMotors,
+                self.wheel_diameter,
+                self.gearing,
+            ),
+            disconnected,
+        }
+    }
+}
+
+impl<const N: usize> Drive<N> {
+    /// Blocks boot on a red screen listing the drive motors that aren't plugged in, if there are
+    /// any, so that a loose cable is caught before the match instead of in the logs afterwards.
+    pub async fn warn_disconnected(&self, display: &mut Display) {
+        if self.disconnected.is_empty() {
+            return;
+        }
+
+        let ports = self
+            .disconnected
+            .iter()
+            .map(|port| port.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        display.erase((255, 0, 0));
+        let detail = format!("ports {ports}");
+        for (line, text) in ["Drive motors unplugged", detail.as_str()]
+            .iter()
+            .enumerate()
+        {
+            display.draw_text(
+                &Text::new(
+                    text,
+                    Font::new(FontSize::MEDIUM, FontFamily::Monospace),
+                    [40, 70 + 30 * line as i16],
+                ),
+                (255, 255, 255),
+                Some((255, 0, 0).into()),
+            );
+        }
+        sleep(WARNING_DISPLAY_TIME).await;
+        display.erase((0, 0, 0));
+    }
+}
@@ -0,0 +1,119 @@
+use std::{cell::RefCell, f64::consts::PI, rc::Rc};
+
+use vexide::{prelude::*, smart::PortError};
+
+/// A shared collection of motors on one side of the drivetrain.
+pub type SharedMotors = Rc<RefCell<dyn AsMut<[Motor]>>>;
+
+/// Shared handles to the left and right drive motors.
+///
+/// These point to the same motors that the drivetrain model and tracking wheels use, and are used
+/// by subsystems that need direct feedback from (or control over) the drive motors.
+#[derive(Clone)]
+pub struct DriveMotors {
+    left: SharedMotors,
+    right: SharedMotors,
+
+    /// Diameter of the drive wheels.
+    pub wheel_diameter: f64,
+
+    /// External gearing between the motors and the drive wheels.
+    pub gearing: f64,
+}
+
+impl DriveMotors {
+    pub fn new(left: SharedMotors, right: SharedMotors, wheel_diameter: f64, gearing: f64) -> Self {
+        Self {
+            left,
+            right,
+            wheel_diameter,
+            gearing,
+        }
+    }
+
+    /// Average linear velocity of the left and right wheels in inches per second.
+    ///
+    /// Returns `None` if no drive motor could be read.
+    pub fn wheel_velocity(&self) -> Option<f64> {
+        let (left, right) = self.side_velocities();
+        match (left, right) {
+            (Some(left), Some(right)) => Some((left + right) / 2.0),
+            (side, None) | (None, side) => side,
+        }
+    }
+
+    /// Linear velocity of the left and right wheels in inches per second.
+    pub fn side_velocities(&self) -> (Option<f64>, Option<f64>) {
+        (
+            self.side_velocity(&self.left),
+            self.side_velocity(&self.right),
+        )
+    }
+
+    /// Distance the left and right wheels have travelled in inches.
+    ///
+    /// A side is `None` if none of its motors could be read.
+    pub fn side_positions(&self) -> (Option<f64>, Option<f64>) {
+        (
+            self.side_position(&self.left),
+            self.side_position(&self.right),
+        )
+    }
+
+    /// Brakes every drive motor using the given brake mode.
+    ///
+    /// Every motor is told to brake even if some of them fail, the last error is returned.
+    pub fn brake(&self, mode: BrakeMode) -> Result<(), PortError> {
+        let mut result = Ok(());
+        for side in [&self.left, &self.right] {
+            for motor in side.borrow_mut().as_mut() {
+                if let Err(e) = motor.brake(mode) {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    fn side_velocity(&self, side: &SharedMotors) -> Option<f64> {
+        let mut side = side.borrow_mut();
+        let rpms = side
+            .as_mut()
+            .iter()
+            .filter_map(|motor| motor.velocity().ok())
+            .collect::<Vec<_>>();
+
+        if rpms.is_empty() {
+            return None;
+        }
+
+        let rpm = rpms.iter().sum::<f64>() / rpms.len() as f64;
+        Some(rpm / 60.0 * self.gearing * self.wheel_diameter * PI)
+    }
+
+    fn side_position(&self, side: &SharedMotors) -> Option<f64> {
+        let mut side = side.borrow_mut();
+        let positions = side
+            .as_mut()
+            .iter()
+            .filter_map(|motor| motor.position().ok())
+            .map(|position| position.as_radians())
+            .collect::<Vec<_>>();
+
+        if positions.is_empty() {
+            return None;
+        }
+
+        let radians = positions.iter().sum::<f64>() / positions.len() as f64;
+        Some(radians * self.gearing * self.wheel_diameter / 2.0)
+    }
+}
+
+/// Returns the brake mode that comes after `mode` in the driver's coast, brake, hold cycle.
+pub fn next_brake_mode(mode: BrakeMode) -> BrakeMode {
+    match mode {
+        BrakeMode::Coast => BrakeMode::Brake,
+        BrakeMode::Brake => BrakeMode::Hold,
+        BrakeMode::Hold => BrakeMode::Coast,
+    }
+}
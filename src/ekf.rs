@@ -0,0 +1,430 @@
+//! Pose estimation with an extended Kalman filter.
+//!
+//! This module only depends on `std` so that it can be run on a computer against synthetic data
+//! (see `host/` and the tests at the bottom of this file).
+
+use std::f64::consts::{PI, TAU};
+
+type Matrix = [[f64; 3]; 3];
+
+/// Standard deviation of odometry's linear error per inch travelled.
+///
+/// Most of odometry's error is a steady bias (a slightly wrong wheel diameter, heading drift)
+/// rather than random noise, so this is larger than the wheel noise alone. It was picked so that
+/// the filter's reported uncertainty matches its actual error in the simulation in the tests below.
+pub const LINEAR_NOISE: f64 = 0.2;
+
+/// Standard deviation of odometry's heading error per radian turned, picked the same way as
+/// [`LINEAR_NOISE`].
+pub const ANGULAR_NOISE: f64 = 0.1;
+
+/// Largest Mahalanobis distance a GPS reading can be from the estimate to be accepted.
+pub const GATE: f64 = 4.0;
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// A robot pose on the field, with `heading` in radians (counterclockwise-positive from the x
+/// axis).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+}
+
+/// Pose Kalman Filter
+///
+/// Fuses relative motion from odometry with absolute pose measurements (such as those from the VEX
+/// GPS sensor). Odometry is smooth but drifts without bound, while absolute measurements are noisy
+/// but don't drift, so the filter follows odometry from moment to moment and lets measurements
+/// slowly pull it back to where the robot actually is.
+///
+/// Measurements that are implausibly far from the current estimate (by Mahalanobis distance) are
+/// rejected, since the GPS can jump when its view of the field strip is blocked.
+///
+/// # Constants
+///
+/// * `linear_noise` - Standard deviation of odometry's linear error per inch travelled.
+/// * `angular_noise` - Standard deviation of odometry's heading error per radian turned.
+/// * `gate` - Largest Mahalanobis distance (in standard deviations) a measurement can be from the
+///   estimate to be accepted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ekf {
+    pub linear_noise: f64,
+    pub angular_noise: f64,
+    pub gate: f64,
+
+    pose: Pose,
+    covariance: Matrix,
+}
+
+impl Ekf {
+    /// Constructs a filter that is certain the robot starts at `pose`.
+    pub fn new(pose: Pose, linear_noise: f64, angular_noise: f64, gate: f64) -> Self {
+        Self {
+            linear_noise,
+            angular_noise,
+            gate,
+            pose,
+            covariance: [[0.0; 3]; 3],
+        }
+    }
+
+    /// The current pose estimate.
+    pub fn pose(&self) -> Pose {
+        self.pose
+    }
+
+    /// Standard deviations of the x, y, and heading estimates.
+    pub fn uncertainty(&self) -> [f64; 3] {
+        [0, 1, 2].map(|i| self.covariance[i][i].sqrt())
+    }
+
+    /// Moves the estimate to `pose` and forgets any uncertainty.
+    pub fn reset(&mut self, pose: Pose) {
+        self.pose = pose;
+        self.covariance = [[0.0; 3]; 3];
+    }
+
+    /// Adds uncertainty to the estimate, for when the robot has moved in a way odometry couldn't
+    /// measure.
+    ///
+    /// `position_error` and `heading_error` are the standard deviations of the added error.
+    pub fn inflate(&mut self, position_error: f64, heading_error: f64) {
+        self.covariance[0][0] += position_error.powi(2);
+        self.covariance[1][1] += position_error.powi(2);
+        self.covariance[2][2] += heading_error.powi(2);
+    }
+
+    /// Advances the estimate by a motion measured by odometry.
+    ///
+    /// `forward` and `sideways` are the distances travelled relative to the robot's heading at the
+    /// start of the motion, and `turn` is the change in heading in radians.
+    pub fn predict(&mut self, forward: f64, sideways: f64, turn: f64) {
+        let (sin, cos) = self.pose.heading.sin_cos();
+
+        self.pose.x += forward * cos - sideways * sin;
+        self.pose.y += forward * sin + sideways * cos;
+        self.pose.heading = wrap(self.pose.heading + turn);
+
+        // jacobian of the motion with respect to the state, only heading affects the other terms
+        let jacobian = [
+            [1.0, 0.0, -forward * sin - sideways * cos],
+            [0.0, 1.0, forward * cos - sideways * sin],
+            [0.0, 0.0, 1.0],
+        ];
+
+        let distance = forward.hypot(sideways);
+        let linear_variance = (self.linear_noise * distance).powi(2);
+        let angular_variance = (self.angular_noise * turn.abs()).powi(2);
+        let noise = [
+            [linear_variance, 0.0, 0.0],
+            [0.0, linear_variance, 0.0],
+            [0.0, 0.0, angular_variance],
+        ];
+
+        self.covariance = add(
+            &multiply(
+                &multiply(&jacobian, &self.covariance),
+                &transpose(&jacobian),
+            ),
+            &noise,
+        );
+    }
+
+    /// Corrects the estimate using an absolute pose measurement.
+    ///
+    /// `position_error` and `heading_error` are the standard deviations of the measurement.
+    /// Returns `false` if the measurement was rejected for being too far from the estimate.
+    pub fn correct(&mut self, measurement: Pose, position_error: f64, heading_error: f64) -> bool {
+        let innovation = [
+            measurement.x - self.pose.x,
+            measurement.y - self.pose.y,
+            wrap(measurement.heading - self.pose.heading),
+        ];
+
+        let noise = [
+            [position_error.powi(2), 0.0, 0.0],
+            [0.0, position_error.powi(2), 0.0],
+            [0.0, 0.0, heading_error.powi(2)],
+        ];
+        let Some(inverse) = invert(&add(&self.covariance, &noise)) else {
+            return false;
+        };
+
+        let distance = dot(&innovation, &apply(&inverse, &innovation)).sqrt();
+        if distance > self.gate {
+            return false;
+        }
+
+        // the measurement observes the state directly, so the kalman gain is just P(P + R)^-1
+        let gain = multiply(&self.covariance, &inverse);
+        let correction = apply(&gain, &innovation);
+
+        self.pose.x += correction[0];
+        self.pose.y += correction[1];
+        self.pose.heading = wrap(self.pose.heading + correction[2]);
+        self.covariance = multiply(&subtract(&IDENTITY, &gain), &self.covariance);
+
+        true
+    }
+}
+
+/// Wraps an angle in radians to `[-π, π)`.
+pub fn wrap(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    out
+}
+
+fn transpose(a: &Matrix) -> Matrix {
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[j][i];
+        }
+    }
+    out
+}
+
+fn add(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = *a;
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value += b[i][j];
+        }
+    }
+    out
+}
+
+fn subtract(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = *a;
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value -= b[i][j];
+        }
+    }
+    out
+}
+
+fn apply(a: &Matrix, v: &[f64; 3]) -> [f64; 3] {
+    [0, 1, 2].map(|i| dot(&a[i], v))
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn invert(a: &Matrix) -> Option<Matrix> {
+    let cofactor = |i: usize, j: usize| {
+        let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+        let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+        a[r0][c0] * a[r1][c1] - a[r0][c1] * a[r1][c0]
+    };
+
+    let determinant = (0..3).map(|j| a[0][j] * cofactor(0, j)).sum::<f64>();
+    if determinant.abs() < f64::EPSILON {
+        return None;
+    }
+
+    // the inverse is the transposed cofactor matrix over the determinant
+    let mut out = [[0.0; 3]; 3];
+    for (i, row) in out.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = cofactor(j, i) / determinant;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::rng::Rng;
+
+    const DT: f64 = 0.01;
+    const DURATION: f64 = 60.0;
+
+    // the filter starts out certain, so skip the first few seconds before comparing its error to
+    // its uncertainty
+    const SETTLE: f64 = 5.0;
+
+    // odometry error model
+    const WHEEL_SCALE_ERROR: f64 = 0.03;
+    const HEADING_DRIFT: f64 = 0.002;
+    const ODOMETRY_NOISE: f64 = 0.02;
+
+    // gps error model
+    const GPS_INTERVAL: f64 = 0.05;
+    const GPS_POSITION_NOISE: f64 = 1.0;
+    const GPS_HEADING_NOISE: f64 = 0.02;
+    const GPS_OUTLIER_CHANCE: f64 = 0.02;
+    const GPS_OUTLIER_SIZE: f64 = 30.0;
+
+    const SEEDS: std::ops::RangeInclusive<u64> = 1..=10;
+
+    #[derive(Debug, Default)]
+    struct Run {
+        /// RMS position error of raw odometry.
+        odometry_error: f64,
+        /// RMS position and heading error of the filter.
+        position_error: f64,
+        heading_error: f64,
+        /// RMS of the filter's reported position and heading standard deviations.
+        position_sigma: f64,
+        heading_sigma: f64,
+        /// GPS readings taken, how many of them were outliers, and how many were rejected.
+        readings: usize,
+        outliers: usize,
+        rejected: usize,
+    }
+
+    /// Drives a simulated robot around the field with drifting odometry and a noisy GPS.
+    fn simulate(seed: u64) -> Run {
+        let mut rng = Rng::new(seed);
+
+        let start = Pose {
+            x: -48.0,
+            y: 0.0,
+            heading: FRAC_PI_2,
+        };
+        let mut truth = start;
+        let mut odometry = start;
+        let mut ekf = Ekf::new(start, LINEAR_NOISE, ANGULAR_NOISE, GATE);
+
+        let mut run = Run::default();
+        let mut steps = 0;
+
+        let mut time = 0.0;
+        let mut next_gps = 0.0;
+        while time < DURATION {
+            // weave back and forth across the field
+            let forward = 30.0 * DT;
+            let turn = 0.8 * (time * 0.5).sin() * DT;
+            truth = step(truth, forward, turn);
+
+            // odometry overestimates travel and slowly drifts in heading
+            let measured_forward =
+                forward * (1.0 + WHEEL_SCALE_ERROR) + rng.gaussian(ODOMETRY_NOISE);
+            let measured_turn = turn + HEADING_DRIFT * DT + rng.gaussian(ODOMETRY_NOISE * DT);
+            odometry = step(odometry, measured_forward, measured_turn);
+            ekf.predict(measured_forward, 0.0, measured_turn);
+
+            if time >= next_gps {
+                next_gps += GPS_INTERVAL;
+
+                let outlier = rng.uniform() < GPS_OUTLIER_CHANCE;
+                let measurement = Pose {
+                    x: truth.x
+                        + rng.gaussian(GPS_POSITION_NOISE)
+                        + if outlier { GPS_OUTLIER_SIZE } else { 0.0 },
+                    y: truth.y + rng.gaussian(GPS_POSITION_NOISE),
+                    heading: wrap(truth.heading + rng.gaussian(GPS_HEADING_NOISE)),
+                };
+
+                run.readings += 1;
+                run.outliers += usize::from(outlier);
+                if !ekf.correct(measurement, GPS_POSITION_NOISE, GPS_HEADING_NOISE) {
+                    run.rejected += 1;
+                }
+            }
+
+            if time >= SETTLE {
+                let [sigma_x, sigma_y, sigma_heading] = ekf.uncertainty();
+                run.odometry_error += distance(odometry, truth).powi(2);
+                run.position_error += distance(ekf.pose(), truth).powi(2);
+                run.heading_error += wrap(ekf.pose().heading - truth.heading).powi(2);
+                run.position_sigma += sigma_x.powi(2) + sigma_y.powi(2);
+                run.heading_sigma += sigma_heading.powi(2);
+                steps += 1;
+            }
+            time += DT;
+        }
+
+        for sum in [
+            &mut run.odometry_error,
+            &mut run.position_error,
+            &mut run.heading_error,
+            &mut run.position_sigma,
+            &mut run.heading_sigma,
+        ] {
+            *sum = (*sum / steps as f64).sqrt();
+        }
+        run
+    }
+
+    fn step(pose: Pose, forward: f64, turn: f64) -> Pose {
+        Pose {
+            x: pose.x + forward * pose.heading.cos(),
+            y: pose.y + forward * pose.heading.sin(),
+            heading: wrap(pose.heading + turn),
+        }
+    }
+
+    fn distance(a: Pose, b: Pose) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    #[test]
+    fn fused_estimate_stays_close() {
+        for seed in SEEDS {
+            let run = simulate(seed);
+            assert!(run.odometry_error > 10.0, "seed {seed}: {run:?}");
+            assert!(run.position_error < 1.0, "seed {seed}: {run:?}");
+            assert!(
+                run.heading_error < 2f64.to_radians(),
+                "seed {seed}: {run:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn uncertainty_matches_error() {
+        // one run's error is noisy, so compare the averages over every seed
+        let runs = SEEDS.map(simulate).collect::<Vec<_>>();
+        let mean = |field: fn(&Run) -> f64| runs.iter().map(field).sum::<f64>() / runs.len() as f64;
+
+        let position = mean(|run| run.position_error) / mean(|run| run.position_sigma);
+        let heading = mean(|run| run.heading_error) / mean(|run| run.heading_sigma);
+        assert!(
+            (0.5..2.0).contains(&position),
+            "position error / sigma = {position}"
+        );
+        assert!(
+            (0.5..2.0).contains(&heading),
+            "heading error / sigma = {heading}"
+        );
+    }
+
+    #[test]
+    fn outliers_are_rejected() {
+        for seed in SEEDS {
+            let run = simulate(seed);
+            assert!(run.outliers > 0, "seed {seed}: {run:?}");
+            // every outlier is rejected, and hardly any good readings are
+            assert!(run.rejected >= run.outliers, "seed {seed}: {run:?}");
+            assert!(
+                run.rejected - run.outliers < run.readings / 100,
+                "seed {seed}: {run:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrap_stays_in_range() {
+        assert_eq!(wrap(0.0), 0.0);
+        assert!((wrap(3.0 * PI / 2.0) + FRAC_PI_2).abs() < 1e-12);
+        assert!((wrap(-5.0 * PI / 2.0) + FRAC_PI_2).abs() < 1e-12);
+        assert_eq!(wrap(PI), -PI);
+    }
+}
@@ -0,0 +1,96 @@
+//! Push Back field geometry.
+//!
+//! Coordinates are in inches with the origin at the center of the field. Only the parts of the
+//! field tall enough to be seen by the distance sensors are included (the perimeter and the goals),
+//! so the matchloaders and park zone barriers are left out.
+//!
+//! This module only depends on `std` so that it can be shared with `host/`.
+
+/// Distance from the center of the field to the inside of each perimeter wall.
+pub const WALL: f64 = 70.2;
+
+/// A straight edge on the field that blocks distance sensors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    pub start: [f64; 2],
+    pub end: [f64; 2],
+}
+
+impl Segment {
+    const fn new(start: [f64; 2], end: [f64; 2]) -> Self {
+        Self { start, end }
+    }
+}
+
+/// Half of the length and width of each long goal.
+const LONG_GOAL_HALF_LENGTH: f64 = 24.0;
+const LONG_GOAL_HALF_WIDTH: f64 = 2.5;
+const LONG_GOAL_Y: f64 = 48.0;
+
+/// Half of the span of each center goal along each axis.
+const CENTER_GOAL_HALF_SPAN: f64 = 8.5;
+
+/// Every edge on the field.
+pub const SEGMENTS: &[Segment] = &[
+    // perimeter
+    Segment::new([-WALL, -WALL], [WALL, -WALL]),
+    Segment::new([WALL, -WALL], [WALL, WALL]),
+    Segment::new([WALL, WALL], [-WALL, WALL]),
+    Segment::new([-WALL, WALL], [-WALL, -WALL]),
+    // long goals
+    Segment::new(
+        [-LONG_GOAL_HALF_LENGTH, LONG_GOAL_Y - LONG_GOAL_HALF_WIDTH],
+        [LONG_GOAL_HALF_LENGTH, LONG_GOAL_Y - LONG_GOAL_HALF_WIDTH],
+    ),
+    Segment::new(
+        [-LONG_GOAL_HALF_LENGTH, LONG_GOAL_Y + LONG_GOAL_HALF_WIDTH],
+        [LONG_GOAL_HALF_LENGTH, LONG_GOAL_Y + LONG_GOAL_HALF_WIDTH],
+    ),
+    Segment::new(
+        [-LONG_GOAL_HALF_LENGTH, -LONG_GOAL_Y - LONG_GOAL_HALF_WIDTH],
+        [LONG_GOAL_HALF_LENGTH, -LONG_GOAL_Y - LONG_GOAL_HALF_WIDTH],
+    ),
+    Segment::new(
+        [-LONG_GOAL_HALF_LENGTH, -LONG_GOAL_Y + LONG_GOAL_HALF_WIDTH],
+        [LONG_GOAL_HALF_LENGTH, -LONG_GOAL_Y + LONG_GOAL_HALF_WIDTH],
+    ),
+    // center goals
+    Segment::new(
+        [-CENTER_GOAL_HALF_SPAN, -CENTER_GOAL_HALF_SPAN],
+        [CENTER_GOAL_HALF_SPAN, CENTER_GOAL_HALF_SPAN],
+    ),
+    Segment::new(
+        [-CENTER_GOAL_HALF_SPAN, CENTER_GOAL_HALF_SPAN],
+        [CENTER_GOAL_HALF_SPAN, -CENTER_GOAL_HALF_SPAN],
+    ),
+];
+
+/// Distance from `origin` to the first edge along a ray pointing in `angle` (radians,
+/// counterclockwise from the x axis).
+///
+/// Returns `None` if the ray doesn't hit anything, which only happens if `origin` is outside the
+/// field.
+pub fn raycast(origin: [f64; 2], angle: f64) -> Option<f64> {
+    let (sin, cos) = angle.sin_cos();
+
+    SEGMENTS
+        .iter()
+        .filter_map(|segment| {
+            let edge = [
+                segment.end[0] - segment.start[0],
+                segment.end[1] - segment.start[1],
+            ];
+            let offset = [segment.start[0] - origin[0], segment.start[1] - origin[1]];
+
+            // solve origin + t * direction = start + u * edge
+            let denominator = cos * edge[1] - sin * edge[0];
+            if denominator.abs() < f64::EPSILON {
+                return None;
+            }
+            let t = (offset[0] * edge[1] - offset[1] * edge[0]) / denominator;
+            let u = (offset[0] * sin - offset[1] * cos) / denominator;
+
+            (t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+        })
+        .min_by(f64::total_cmp)
+}
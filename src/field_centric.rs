@@ -0,0 +1,99 @@
+use std::{f64::consts::FRAC_PI_2, time::Instant};
+
+use evian::{
+    control::loops::{AngularPid, Feedback},
+    math::Angle,
+};
+
+use crate::steering::turn_power;
+
+/// Field-Centric Drive
+///
+/// Maps the direction of a joystick to a direction on the field rather than a direction relative to
+/// the robot. Pushing the stick straight up always drives towards [`FieldCentric::forward`],
+/// regardless of which way the robot is facing. The robot turns towards the commanded direction
+/// while driving, so the differential drivetrain handles much like a holonomic one.
+///
+/// Since the drivetrain drives just as well backwards as it does forwards, the robot will reverse
+/// towards the commanded direction whenever that requires less turning.
+///
+/// The robot's heading is read from odometry, so the field frame is whatever frame tracking was
+/// last set to (usually by the autonomous route that ran before driver control).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldCentric {
+    /// Whether the joystick should be interpreted as a field direction.
+    pub enabled: bool,
+
+    /// Controller used to turn towards the commanded direction.
+    pub controller: AngularPid,
+
+    /// Minimum joystick magnitude to not ignore, creates a deadzone at the center of the joystick.
+    pub deadzone: f64,
+
+    /// Field heading that pushing the joystick straight up drives towards.
+    pub forward: Angle,
+
+    active: Option<(AngularPid, Instant)>,
+}
+
+impl FieldCentric {
+    /// Constructs a disabled [`FieldCentric`] with the provided controller, deadzone, and forward
+    /// direction.
+    pub fn new(controller: AngularPid, deadzone: f64, forward: Angle) -> Self {
+        Self {
+            enabled: false,
+            controller,
+            deadzone,
+            forward,
+            active: None,
+        }
+    }
+
+    /// Enables or disables field-centric driving.
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.active = None;
+    }
+
+    /// Computes the `(linear, angular)` arcade powers needed to drive in the direction of the
+    /// joystick.
+    ///
+    /// The sign of the angular power matches a turn stick (positive turns clockwise). Returns
+    /// `None` while field-centric driving is disabled or the joystick is inside the deadzone, so
+    /// that the caller can fall back to another drive algorithm.
+    pub fn update(&mut self, heading: Angle, x: f64, y: f64) -> Option<(f64, f64)> {
+        let magnitude = x.hypot(y).min(1.0);
+        if !self.enabled || magnitude < self.deadzone {
+            self.active = None;
+            return None;
+        }
+
+        // the joystick is in standard position with up at 90 degrees, rotate it so up is `forward`
+        let mut target = self.forward + Angle::atan2(y, x) - Angle::QUARTER_TURN;
+        let mut direction = 1.0;
+
+        // back towards the target if it's behind us
+        if (target - heading).wrapped().as_radians().abs() > FRAC_PI_2 {
+            target += Angle::HALF_TURN;
+            direction = -1.0;
+        }
+
+        // only drive once we're roughly facing the target, and slow down the more we need to turn
+        let error = (target - heading).wrapped();
+        let linear = direction * magnitude * error.cos();
+
+        let now = Instant::now();
+        let (controller, prev_time) = self.active.get_or_insert((self.controller, now));
+        let dt = now - *prev_time;
+        *prev_time = now;
+
+        // skip the first update, the controller can't differentiate over no time
+        if dt.is_zero() {
+            return Some((linear, 0.0));
+        }
+
+        let angular = turn_power(controller.update(heading, target, dt));
+
+        Some((linear, angular))
+    }
+}
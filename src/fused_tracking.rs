@@ -0,0 +1,389 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use evian::{
+    math::{Angle, Vec2},
+    prelude::*,
+    tracking::{Tracking, sensor::Gyro, wheeled::WheeledTracking},
+};
+use log::{info, warn};
+use vexide::{prelude::*, task::Task};
+
+use crate::{
+    drive_motors::DriveMotors,
+    ekf::{Ekf, Pose},
+    imu::SharedImu,
+};
+
+const INCHES_PER_METER: f64 = 39.3701;
+
+/// How often odometry is folded into the filter and the GPS is checked.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A GPS sensor along with the limits on when its readings are trusted.
+pub struct GpsSource {
+    pub sensor: GpsSensor,
+
+    /// Rotation from the GPS's field frame to the frame odometry is tracked in.
+    pub rotation: Angle,
+
+    /// Readings are rejected while the sensor reports more error than this, in inches.
+    pub max_error: f64,
+
+    /// Readings are rejected while the robot is moving faster than this, in inches per second.
+    /// The GPS lags behind the robot, so fast readings are stale by the time they arrive.
+    pub max_speed: f64,
+
+    /// Standard deviation of the GPS's heading readings.
+    pub heading_error: Angle,
+}
+
+/// Where heading comes from while the IMU odometry uses can't be read.
+///
+/// Odometry can't switch gyros once it's built, so while the IMU is unplugged the turn between
+/// updates is measured from the difference in travel of the left and right drive wheels instead,
+/// and odometry's own heading is ignored. The update the IMU comes back on is measured from the
+/// wheels too, so that a jump in odometry's heading isn't taken as a turn.
+pub struct HeadingFallback {
+    imu: SharedImu,
+    motors: DriveMotors,
+    track_width: f64,
+
+    // whether the imu couldn't be read at the last update
+    lost: bool,
+    prev_sides: Option<(f64, f64)>,
+}
+
+impl HeadingFallback {
+    /// Watches `imu`, which must be the same handle odometry was built with.
+    ///
+    /// * `track_width` - Distance between the left and right drive wheels.
+    pub fn new(imu: SharedImu, motors: DriveMotors, track_width: f64) -> Self {
+        Self {
+            imu,
+            motors,
+            track_width,
+            lost: false,
+            prev_sides: None,
+        }
+    }
+
+    /// Returns the turn in radians measured by the drive wheels since the last update, if it
+    /// should be used instead of odometry's.
+    fn turn(&mut self) -> Option<f64> {
+        let sides = match self.motors.side_positions() {
+            (Some(left), Some(right)) => Some((left, right)),
+            _ => None,
+        };
+        let wheel_turn = match (self.prev_sides, sides) {
+            (Some((prev_left, prev_right)), Some((left, right))) => {
+                ((right - prev_right) - (left - prev_left)) / self.track_width
+            }
+            _ => 0.0,
+        };
+        self.prev_sides = sides;
+
+        let lost = self.imu.heading().is_err();
+        if lost != self.lost {
+            if lost {
+                warn!("imu disconnected, falling back to wheel heading");
+            } else {
+                info!("imu reconnected");
+            }
+        }
+
+        let recovering = self.lost;
+        self.lost = lost;
+        (lost || recovering).then_some(wheel_turn)
+    }
+}
+
+/// GPS-Fused Tracking
+///
+/// Wraps wheel/IMU odometry and, when a GPS sensor is available, fuses its readings into the pose
+/// with an [`Ekf`]. Without a GPS this reports exactly what odometry reports.
+///
+/// With a [`HeadingFallback`], turns are measured by the drive wheels while the IMU is unplugged.
+///
+/// Motions only see the fused pose, so they can be used with this just like with
+/// [`WheeledTracking`]. Forward travel and velocities always come straight from odometry, since the
+/// GPS can only correct where the robot is, not how it's moving.
+pub struct FusedTracking {
+    odometry: Rc<RefCell<WheeledTracking>>,
+    state: Rc<RefCell<FusedState>>,
+    _task: Task<()>,
+}
+
+struct FusedState {
+    ekf: Ekf,
+
+    // odometry pose at the last update, used to find how far the robot moved since
+    prev_position: Vec2<f64>,
+    prev_heading: Angle,
+
+    // whether the last gps reading was rejected, so rejections are only logged once
+    rejecting: bool,
+
+    heading_fallback: Option<HeadingFallback>,
+
+    // bumped every time the pose is reset, so readers can tell a jump from motion
+    resets: u32,
+}
+
+impl FusedTracking {
+    /// Starts fusing `odometry` with an optional GPS source, measuring heading with
+    /// `heading_fallback` while the IMU is unplugged.
+    ///
+    /// # Constants
+    ///
+    /// * `linear_noise` - Standard deviation of odometry's linear error per inch travelled.
+    /// * `angular_noise` - Standard deviation of odometry's heading error per radian turned.
+    /// * `gate` - Largest Mahalanobis distance a GPS reading can be from the estimate to be
+    ///   accepted.
+    pub fn new(
+        odometry: WheeledTracking,
+        gps: Option<GpsSource>,
+        heading_fallback: Option<HeadingFallback>,
+        linear_noise: f64,
+        angular_noise: f64,
+        gate: f64,
+    ) -> Self {
+        let prev_position = odometry.position();
+        let prev_heading = odometry.heading();
+
+        let odometry = Rc::new(RefCell::new(odometry));
+        let state = Rc::new(RefCell::new(FusedState {
+            ekf: Ekf::new(
+                pose(prev_position, prev_heading),
+                linear_noise,
+                angular_noise,
+                gate,
+            ),
+            prev_position,
+            prev_heading,
+            rejecting: false,
+            heading_fallback,
+            resets: 0,
+        }));
+
+        Self {
+            _task: spawn(Self::task(odometry.clone(), state.clone(), gps)),
+            odometry,
+            state,
+        }
+    }
+
+    async fn task(
+        odometry: Rc<RefCell<WheeledTracking>>,
+        state: Rc<RefCell<FusedState>>,
+        gps: Option<GpsSource>,
+    ) {
+        loop {
+            {
+                let odometry = odometry.borrow();
+                let mut state = state.borrow_mut();
+                state.predict(&odometry);
+
+                if let Some(gps) = &gps {
+                    state.correct(gps, odometry.linear_velocity());
+                }
+            }
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+    }
+
+    /// Returns a handle that can read the fused pose from other tasks.
+    pub fn reader(&self) -> TrackingReader {
+        TrackingReader {
+            odometry: self.odometry.clone(),
+            state: self.state.clone(),
+        }
+    }
+
+    /// Moves the tracked position to `position`, resetting both odometry and the filter.
+    pub fn set_position(&mut self, position: impl Into<Vec2<f64>>) {
+        let position = position.into();
+        self.sync();
+        self.odometry.borrow_mut().set_position(position);
+
+        let mut state = self.state.borrow_mut();
+        let heading = state.ekf.pose().heading;
+        state.ekf.reset(Pose {
+            x: position.x,
+            y: position.y,
+            heading,
+        });
+        state.prev_position = position;
+        state.resets += 1;
+    }
+
+    /// Moves the tracked heading to `heading`, resetting both odometry and the filter.
+    pub fn set_heading(&mut self, heading: Angle) {
+        self.sync();
+        self.odometry.borrow_mut().set_heading(heading);
+
+        let mut state = self.state.borrow_mut();
+        let pose = state.ekf.pose();
+        state.ekf.reset(Pose {
+            heading: heading.wrapped().as_radians(),
+            ..pose
+        });
+        state.prev_heading = heading;
+        state.resets += 1;
+    }
+
+    /// Lowers confidence in the tracked pose after a disturbance odometry couldn't measure, so that
+    /// GPS readings pull it back harder (and far-off readings aren't rejected).
+    ///
+    /// `position_error` is the standard deviation of the added position error in inches.
+    pub fn lower_confidence(&mut self, position_error: f64, heading_error: Angle) {
+        self.state
+            .borrow_mut()
+            .ekf
+            .inflate(position_error, heading_error.as_radians());
+    }
+
+    /// Folds in any odometry motion that happened since the last update, so that nothing is lost
+    /// when odometry is reset.
+    fn sync(&self) {
+        self.state.borrow_mut().predict(&self.odometry.borrow());
+    }
+}
+
+/// A read-only handle to the pose tracked by a [`FusedTracking`].
+#[derive(Clone)]
+pub struct TrackingReader {
+    odometry: Rc<RefCell<WheeledTracking>>,
+    state: Rc<RefCell<FusedState>>,
+}
+
+impl TrackingReader {
+    /// The current fused pose.
+    pub fn pose(&self) -> Pose {
+        self.state.borrow().ekf.pose()
+    }
+
+    /// How many times the pose has been moved with [`FusedTracking::set_position`] or
+    /// [`FusedTracking::set_heading`].
+    ///
+    /// Anything following the pose's motion should start over from the current pose when this
+    /// changes, since the jump isn't motion the robot made.
+    pub fn resets(&self) -> u32 {
+        self.state.borrow().resets
+    }
+
+    /// Linear velocity from odometry in inches per second.
+    pub fn linear_velocity(&self) -> f64 {
+        self.odometry.borrow().linear_velocity()
+    }
+
+    /// Angular velocity from odometry in radians per second.
+    pub fn angular_velocity(&self) -> f64 {
+        self.odometry.borrow().angular_velocity()
+    }
+}
+
+impl FusedState {
+    fn predict(&mut self, odometry: &WheeledTracking) {
+        let position = odometry.position();
+        let heading = odometry.heading();
+
+        // express the motion relative to where odometry thought the robot was facing, so it can
+        // be applied to the fused heading instead
+        let delta = position - self.prev_position;
+        let (sin, cos) = self.prev_heading.as_radians().sin_cos();
+        let forward = delta.x * cos + delta.y * sin;
+        let sideways = -delta.x * sin + delta.y * cos;
+        let turn = self
+            .heading_fallback
+            .as_mut()
+            .and_then(HeadingFallback::turn)
+            .unwrap_or_else(|| (heading - self.prev_heading).wrapped().as_radians());
+
+        self.ekf.predict(forward, sideways, turn);
+        self.prev_position = position;
+        self.prev_heading = heading;
+    }
+
+    fn correct(&mut self, gps: &GpsSource, linear_velocity: f64) {
+        if linear_velocity.abs() > gps.max_speed {
+            return;
+        }
+
+        let (Ok(position), Ok(heading), Ok(error)) = (
+            gps.sensor.position(),
+            gps.sensor.heading(),
+            gps.sensor.error(),
+        ) else {
+            return;
+        };
+
+        let error = error * INCHES_PER_METER;
+        if error > gps.max_error {
+            return;
+        }
+
+        // gps headings are compass headings, clockwise from the y axis
+        let position = Vec2::new(position.x, position.y) * INCHES_PER_METER;
+        let (sin, cos) = gps.rotation.as_radians().sin_cos();
+        let measurement = Pose {
+            x: position.x * cos - position.y * sin,
+            y: position.x * sin + position.y * cos,
+            heading: (gps.rotation + Angle::QUARTER_TURN - heading)
+                .wrapped()
+                .as_radians(),
+        };
+
+        let accepted = self
+            .ekf
+            .correct(measurement, error, gps.heading_error.as_radians());
+        if !accepted && !self.rejecting {
+            let estimate = self.ekf.pose();
+            let [sigma_x, sigma_y, _] = self.ekf.uncertainty();
+            warn!(
+                "rejecting gps reading at ({:.2}, {:.2}), too far from estimate ({:.2} ± {sigma_x:.2}, {:.2} ± {sigma_y:.2})",
+                measurement.x, measurement.y, estimate.x, estimate.y
+            );
+        }
+        self.rejecting = !accepted;
+    }
+}
+
+fn pose(position: Vec2<f64>, heading: Angle) -> Pose {
+    Pose {
+        x: position.x,
+        y: position.y,
+        heading: heading.wrapped().as_radians(),
+    }
+}
+
+impl Tracking for FusedTracking {}
+
+impl TracksPosition for FusedTracking {
+    fn position(&self) -> Vec2<f64> {
+        let pose = self.state.borrow().ekf.pose();
+        Vec2::new(pose.x, pose.y)
+    }
+}
+
+impl TracksHeading for FusedTracking {
+    fn heading(&self) -> Angle {
+        Angle::from_radians(self.state.borrow().ekf.pose().heading)
+    }
+}
+
+impl TracksForwardTravel for FusedTracking {
+    fn forward_travel(&self) -> f64 {
+        self.odometry.borrow().forward_travel()
+    }
+}
+
+impl TracksVelocity for FusedTracking {
+    fn linear_velocity(&self) -> f64 {
+        self.odometry.borrow().linear_velocity()
+    }
+
+    fn angular_velocity(&self) -> f64 {
+        self.odometry.borrow().angular_velocity()
+    }
+}
@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use evian::{
+    control::loops::{AngularPid, Feedback},
+    math::Angle,
+};
+
+use crate::steering::turn_power;
+
+/// Heading Hold Assist
+///
+/// Keeps the robot pointed in a straight line while the driver is only giving throttle input.
+/// Uneven motors or contact with other robots will slowly turn a robot that is being driven
+/// "straight", so when the turn input falls inside the deadzone the current heading is latched and
+/// a small angular PID corrects any drift away from it. As soon as the driver turns again the latch
+/// is released and the driver has full control.
+///
+/// The correction returned by [`HeadingHold::update`] is meant to be added on top of the angular
+/// power of another drive algorithm, such as [`CurvatureDrive::arcade`].
+///
+/// [`CurvatureDrive::arcade`]: crate::curvature::CurvatureDrive::arcade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeadingHold {
+    /// Whether the assist is allowed to latch a heading and apply corrections.
+    pub enabled: bool,
+
+    /// Controller used to correct drift away from the latched heading. A fresh copy of it is used
+    /// every time a new heading is latched.
+    pub controller: AngularPid,
+
+    /// Minimum value for `turn` and `throttle` to be considered intentional driver input.
+    pub deadzone: f64,
+
+    latch: Option<Latch>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Latch {
+    target: Angle,
+    controller: AngularPid,
+    prev_time: Instant,
+}
+
+impl HeadingHold {
+    /// Constructs a disabled [`HeadingHold`] with the provided controller and deadzone.
+    pub fn new(controller: AngularPid, deadzone: f64) -> Self {
+        Self {
+            enabled: false,
+            controller,
+            deadzone,
+            latch: None,
+        }
+    }
+
+    /// Enables or disables the assist, releasing any latched heading.
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.release();
+    }
+
+    /// Forgets the latched heading, if any.
+    pub fn release(&mut self) {
+        self.latch = None;
+    }
+
+    /// Returns the angular power that should be added to the driver's turn output.
+    ///
+    /// The sign of the output matches the driver's turn stick (positive turns clockwise). Zero is
+    /// returned while the assist is disabled, while the driver is turning, or while the robot isn't
+    /// being driven.
+    pub fn update(&mut self, heading: Angle, throttle: f64, turn: f64) -> f64 {
+        if !self.enabled || turn.abs() > self.deadzone || throttle.abs() < self.deadzone {
+            self.release();
+            return 0.0;
+        }
+
+        let now = Instant::now();
+        let Some(latch) = &mut self.latch else {
+            // first update after the driver stopped turning, so there's no drift to correct yet
+            self.latch = Some(Latch {
+                target: heading,
+                controller: self.controller,
+                prev_time: now,
+            });
+            return 0.0;
+        };
+
+        let dt = now - latch.prev_time;
+        latch.prev_time = now;
+
+        turn_power(latch.controller.update(heading, latch.target, dt))
+    }
+}
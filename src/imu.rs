@@ -0,0 +1,214 @@
+use std::{
+    future::{Future, poll_fn},
+    pin::pin,
+    rc::Rc,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use evian::{math::Angle, tracking::sensor::Gyro};
+use log::{info, warn};
+use vexide::{
+    display::{Font, FontFamily, FontSize, Rect, Text},
+    prelude::*,
+};
+
+/// Roughly how long the IMU takes to calibrate, used to fill the progress bar.
+const EXPECTED_CALIBRATION_TIME: Duration = Duration::from_secs(2);
+
+/// How long the failure indicator stays on screen before boot continues.
+const FAILURE_DISPLAY_TIME: Duration = Duration::from_secs(2);
+
+const BAR_MARGIN: i16 = 40;
+const BAR_TOP: i16 = 120;
+const BAR_HEIGHT: i16 = 30;
+
+/// Standard gravity in inches per second squared, used to convert IMU readings.
+const GRAVITY: f64 = 386.0886;
+
+/// How quickly [`ImuVelocity`] is pulled back towards wheel velocity while the wheels are trusted.
+/// Keeps accelerometer drift from building up.
+const VELOCITY_BLEND: f64 = 0.05;
+
+/// Which of the IMU's axes points towards the front of the robot.
+///
+/// The IMU's frame is NED, so with the sensor mounted flat, x is out of its port and y is to the
+/// right of that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImuAxis {
+    X,
+    NegativeX,
+    Y,
+    NegativeY,
+}
+
+impl ImuAxis {
+    /// The robot's tilt in radians as `(pitch, roll)`, where positive pitch means the front of the
+    /// robot is up and positive roll means its right side is down.
+    pub fn tilt(self, imu: &InertialSensor) -> Option<(f64, f64)> {
+        let pitch = imu.pitch().ok()?.as_radians();
+        let roll = imu.roll().ok()?.as_radians();
+
+        // positive pitch lifts the x axis, positive roll drops the y axis
+        Some(match self {
+            Self::X => (pitch, roll),
+            Self::NegativeX => (-pitch, -roll),
+            Self::Y => (-roll, pitch),
+            Self::NegativeY => (roll, -pitch),
+        })
+    }
+
+    /// Forward acceleration of the robot in g, with the part of gravity that the accelerometer
+    /// feels while the robot is tipped forwards or backwards taken out.
+    fn forward_acceleration(self, imu: &InertialSensor) -> Option<f64> {
+        let acceleration = imu.acceleration().ok()?;
+        let (lift, _) = self.tilt(imu)?;
+        let reading = match self {
+            Self::X => acceleration.x,
+            Self::NegativeX => -acceleration.x,
+            Self::Y => acceleration.y,
+            Self::NegativeY => -acceleration.y,
+        };
+
+        // an axis tipped up by some angle feels sin(angle) of gravity pulling back along it, even
+        // when the robot isn't accelerating at all
+        Some(reading - lift.sin())
+    }
+}
+
+/// Forward velocity of the robot in inches per second, estimated by integrating IMU acceleration.
+///
+/// The wheels can't tell when they're slipping or when the robot is being pushed, and the IMU can,
+/// but integrated acceleration drifts. The estimate is pulled back towards wheel velocity with
+/// [`ImuVelocity::blend`] whenever the wheels are known to agree with the IMU, so it's only left
+/// to drift while they don't.
+pub struct ImuVelocity {
+    axis: ImuAxis,
+    velocity: f64,
+}
+
+impl ImuVelocity {
+    /// Starts an estimate at rest, integrating acceleration along `axis`.
+    pub const fn new(axis: ImuAxis) -> Self {
+        Self {
+            axis,
+            velocity: 0.0,
+        }
+    }
+
+    /// The current estimate.
+    pub const fn velocity(&self) -> f64 {
+        self.velocity
+    }
+
+    /// Adds the last `dt` seconds of acceleration to the estimate. Readings that fail are skipped.
+    pub fn integrate(&mut self, imu: &InertialSensor, dt: f64) {
+        if let Some(acceleration) = self.axis.forward_acceleration(imu) {
+            self.velocity += acceleration * GRAVITY * dt;
+        }
+    }
+
+    /// Pulls the estimate part of the way towards `wheel_velocity`.
+    pub fn blend(&mut self, wheel_velocity: f64) {
+        self.velocity += (wheel_velocity - self.velocity) * VELOCITY_BLEND;
+    }
+
+    /// Restarts the estimate from `velocity`.
+    pub fn reset(&mut self, velocity: f64) {
+        self.velocity = velocity;
+    }
+}
+
+/// A handle to the one IMU on the robot, shared between odometry and everything else that reads it.
+///
+/// Odometry takes its gyro by value, so it's given a clone of this rather than the sensor itself.
+#[derive(Clone)]
+pub struct SharedImu(pub Rc<InertialSensor>);
+
+impl Gyro for SharedImu {
+    type Error = <InertialSensor as Gyro>::Error;
+
+    fn heading(&self) -> Result<Angle, Self::Error> {
+        Gyro::heading(&*self.0)
+    }
+
+    fn angular_velocity(&self) -> Result<f64, Self::Error> {
+        Gyro::angular_velocity(&*self.0)
+    }
+}
+
+/// Calibrates the IMU, blocking on a progress screen until it finishes.
+///
+/// The sensor doesn't report how far along calibration is, so the progress bar fills based on how
+/// long calibration usually takes and only completes once the sensor is done. If calibration
+/// fails, the screen turns red for a moment and `false` is returned so that tracking can be built
+/// without the IMU.
+pub async fn calibrate(imu: &mut InertialSensor, display: &mut Display) -> bool {
+    display.erase((0, 0, 0));
+    draw_message(display, "Calibrating IMU...", (0, 0, 0));
+
+    let start = Instant::now();
+    let mut calibration = pin!(imu.calibrate());
+    let result = loop {
+        if let Poll::Ready(result) = poll_fn(|cx| Poll::Ready(calibration.as_mut().poll(cx))).await
+        {
+            break result;
+        }
+
+        // never show a full bar before the sensor is actually done
+        let progress =
+            (start.elapsed().as_secs_f64() / EXPECTED_CALIBRATION_TIME.as_secs_f64()).min(0.95);
+        draw_progress(display, progress);
+
+        sleep(Duration::from_millis(10)).await;
+    };
+
+    match result {
+        Ok(()) => {
+            info!("imu calibrated in {:?}", start.elapsed());
+            draw_progress(display, 1.0);
+            display.erase((0, 0, 0));
+            true
+        }
+        Err(e) => {
+            warn!("couldn't calibrate imu, falling back to wheel heading: {e}");
+            display.erase((255, 0, 0));
+            draw_message(display, "IMU calibration failed", (255, 0, 0));
+            sleep(FAILURE_DISPLAY_TIME).await;
+            display.erase((0, 0, 0));
+            false
+        }
+    }
+}
+
+fn draw_message(display: &mut Display, message: &str, background: (u8, u8, u8)) {
+    display.draw_text(
+        &Text::new(
+            message,
+            Font::new(FontSize::MEDIUM, FontFamily::Monospace),
+            [BAR_MARGIN, BAR_TOP - 50],
+        ),
+        (255, 255, 255),
+        Some(background.into()),
+    );
+}
+
+fn draw_progress(display: &mut Display, progress: f64) {
+    let width = Display::HORIZONTAL_RESOLUTION - 2 * BAR_MARGIN;
+    let filled = (f64::from(width) * progress) as i16;
+
+    display.fill(
+        &Rect::new(
+            [BAR_MARGIN, BAR_TOP],
+            [BAR_MARGIN + width, BAR_TOP + BAR_HEIGHT],
+        ),
+        (64, 64, 64),
+    );
+    display.fill(
+        &Rect::new(
+            [BAR_MARGIN, BAR_TOP],
+            [BAR_MARGIN + filled, BAR_TOP + BAR_HEIGHT],
+        ),
+        (0, 200, 0),
+    );
+}
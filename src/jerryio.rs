@@ -0,0 +1,1306 @@
+//! JerryIO route files.
+//!
+//! Routes are drawn in [JerryIO](https://path.jerryio.com) and exported as one statement per
+//! line, followed by the path data JerryIO needs to open the file again:
+//!
+//! ```text
+//! // Path
+//!
+//! backward(16, 30);
+//! turnTo(79.956, 30);
+//! forward(22.545, 30);
+//!
+//! #PATH.JERRYIO-DATA {...}
+//! ```
+//!
+//! Motions take a distance in inches or an absolute heading in degrees, and a speed as a
+//! percentage of full power. Routes are drawn in JerryIO's "VEX Gaming Positioning System"
+//! coordinates, where headings are compass bearings: 0° points along +y and they increase
+//! clockwise. Every heading is converted to evian's convention (0° along +x, increasing
+//! counterclockwise) as it's parsed, so nothing past this module has to know about bearings.
+//!
+//! Everything else is an action hook. JerryIO doesn't know about hooks and re-exporting a path
+//! overwrites the whole file, so they're kept in an actions file next to the route (like
+//! `skills-route.actions` for `skills-route.txt`). Hooks are grouped under the end point they run
+//! at, named by the uid JerryIO gave it, which stays the same when the path is edited:
+//!
+//! ```text
+//! // score on middle goal
+//! at 5HnU8mPuWK:
+//! intake(score_middle);
+//! wait(500);
+//!
+//! leaving UWHNjjZmGp:
+//! matchloader(extend);
+//! ```
+//!
+//! `at` hooks run when the drive to the end point finishes, before turning away from it, and
+//! `leaving` hooks run after turning, just before driving away. Hooks can still be written
+//! between the motions in the route file, but they're lost the next time it's exported.
+//!
+//! The path data is JSON with every control point JerryIO drew the path through. [`parse_data`]
+//! reads it into [`PathData`] so that the points themselves can be used, not just the motions
+//! generated from them.
+//!
+//! This module only depends on `std` so that it can be shared with `host/` and `build.rs`.
+
+use std::{fmt, time::Duration};
+
+use json::Value;
+
+/// The extension of a route's actions file, which has the same name as the route file.
+pub const ACTIONS_EXTENSION: &str = "actions";
+
+/// The line that starts JerryIO's path data, after the last statement.
+pub const DATA_MARKER: &str = "#PATH.JERRYIO-DATA";
+
+/// The only coordinate system headings are converted from.
+pub const COORDINATE_SYSTEM: &str = "VEX Gaming Positioning System";
+
+/// Half the width of the 144" field, in inches.
+pub const FIELD_HALF_WIDTH: f64 = 72.0;
+
+/// One line of a route.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Statement {
+    /// `forward(distance, speed);`
+    Forward {
+        distance: f64,
+        speed: f64,
+    },
+
+    /// `backward(distance, speed);`
+    Backward {
+        distance: f64,
+        speed: f64,
+    },
+
+    /// `turnTo(heading, speed);`, with the heading converted from a bearing (see
+    /// [`from_bearing`]).
+    TurnTo {
+        heading: f64,
+        speed: f64,
+    },
+
+    Action(Action),
+}
+
+/// Something done between motions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// `intake(collect);`, `intake(score_long);`, `intake(score_middle);`, `intake(score_low);`, or
+    /// `intake(stop);`
+    Intake(IntakeAction),
+
+    /// `matchloader(extend);`
+    ExtendMatchloader,
+
+    /// `matchloader(retract);`
+    RetractMatchloader,
+
+    /// `localize();`, which moves the tracked position onto the particle filter's estimate.
+    Localize,
+
+    /// `color_sort(off);`, for skills where every block is ours.
+    DisableColorSort,
+
+    /// `wait(milliseconds);`
+    Wait(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntakeAction {
+    Collect,
+    ScoreLong,
+    ScoreMiddle,
+    ScoreLow,
+    Stop,
+}
+
+/// Why a route couldn't be parsed, along with the (1-indexed) line it was on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// The line isn't `name(arguments);`.
+    Syntax,
+
+    /// There's no statement with this name.
+    UnknownStatement(String),
+
+    /// The statement was given the wrong number of arguments.
+    ArgumentCount { expected: usize, found: usize },
+
+    /// An argument isn't a number, or is a number the statement can't use.
+    InvalidArgument(String),
+
+    /// `turnLeft` or `turnRight`, which JerryIO only writes when exporting relative headings.
+    RelativeTurn(String),
+
+    /// A motion in an actions file.
+    NotAnAction(String),
+
+    /// An action in an actions file that isn't under an `at` or `leaving` line.
+    MissingEndPoint,
+
+    /// An actions file names an end point that isn't in the path.
+    UnknownEndPoint(String),
+
+    /// An actions file has hooks that can never run, at the start or leaving the end.
+    NeverRuns(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ParseErrorKind::Syntax => write!(f, "expected `name(arguments);`"),
+            ParseErrorKind::UnknownStatement(name) => write!(f, "unknown statement `{name}`"),
+            ParseErrorKind::ArgumentCount { expected, found } => {
+                write!(f, "expected {expected} arguments, found {found}")
+            }
+            ParseErrorKind::InvalidArgument(argument) => write!(f, "invalid argument `{argument}`"),
+            ParseErrorKind::RelativeTurn(name) => write!(
+                f,
+                "`{name}` is a relative turn, export the path with absolute headings instead"
+            ),
+            ParseErrorKind::NotAnAction(name) => {
+                write!(f, "`{name}` is a motion, motions go in the route file")
+            }
+            ParseErrorKind::MissingEndPoint => {
+                write!(f, "expected `at <uid>:` or `leaving <uid>:` before actions")
+            }
+            ParseErrorKind::UnknownEndPoint(uid) => write!(f, "no end point `{uid}` in the path"),
+            ParseErrorKind::NeverRuns(header) => write!(f, "`{header}` hooks never run"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Converts a compass bearing in degrees (0° along +y, increasing clockwise) to a heading in
+/// degrees from 0 to 360 (0° along +x, increasing counterclockwise).
+pub fn from_bearing(bearing: f64) -> f64 {
+    (90.0 - bearing).rem_euclid(360.0)
+}
+
+/// Parses every statement in a route file, stopping at JerryIO's path data.
+pub fn parse(text: &str) -> Result<Vec<Statement>, ParseError> {
+    let mut statements = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        if line.trim_start().starts_with(DATA_MARKER) {
+            break;
+        }
+
+        let line_number = i + 1;
+        let line = match line.split_once("//") {
+            Some((code, _comment)) => code,
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        statements.push(parse_statement(line).map_err(|kind| ParseError {
+            line: line_number,
+            kind,
+        })?);
+    }
+
+    Ok(statements)
+}
+
+fn parse_statement(line: &str) -> Result<Statement, ParseErrorKind> {
+    let (name, arguments) = line
+        .strip_suffix(';')
+        .and_then(|line| line.trim_end().strip_suffix(')'))
+        .and_then(|line| line.split_once('('))
+        .ok_or(ParseErrorKind::Syntax)?;
+    let arguments: Vec<&str> = if arguments.trim().is_empty() {
+        Vec::new()
+    } else {
+        arguments.split(',').map(str::trim).collect()
+    };
+
+    match name.trim() {
+        "forward" => {
+            let [distance, speed] = motion_arguments(&arguments)?;
+            Ok(Statement::Forward { distance, speed })
+        }
+        "backward" => {
+            let [distance, speed] = motion_arguments(&arguments)?;
+            Ok(Statement::Backward { distance, speed })
+        }
+        "turnTo" => {
+            let [bearing, speed] = motion_arguments(&arguments)?;
+            Ok(Statement::TurnTo {
+                heading: from_bearing(bearing),
+                speed,
+            })
+        }
+        // the direction of a relative turn can't be checked against the path, so rather than
+        // guess which way it goes these have to be exported as absolute headings
+        name @ ("turnLeft" | "turnRight") => Err(ParseErrorKind::RelativeTurn(name.to_string())),
+        "intake" => {
+            let [action] = expect_arguments(&arguments)?;
+            Ok(Statement::Action(Action::Intake(match action {
+                "collect" => IntakeAction::Collect,
+                "score_long" => IntakeAction::ScoreLong,
+                "score_middle" => IntakeAction::ScoreMiddle,
+                "score_low" => IntakeAction::ScoreLow,
+                "stop" => IntakeAction::Stop,
+                _ => return Err(ParseErrorKind::InvalidArgument(action.to_string())),
+            })))
+        }
+        "matchloader" => {
+            let [action] = expect_arguments(&arguments)?;
+            match action {
+                "extend" => Ok(Statement::Action(Action::ExtendMatchloader)),
+                "retract" => Ok(Statement::Action(Action::RetractMatchloader)),
+                _ => Err(ParseErrorKind::InvalidArgument(action.to_string())),
+            }
+        }
+        "localize" => {
+            let [] = expect_arguments(&arguments)?;
+            Ok(Statement::Action(Action::Localize))
+        }
+        "color_sort" => match expect_arguments(&arguments)? {
+            ["off"] => Ok(Statement::Action(Action::DisableColorSort)),
+            [argument] => Err(ParseErrorKind::InvalidArgument(argument.to_string())),
+        },
+        "wait" => {
+            let [milliseconds] = expect_arguments(&arguments)?;
+            let milliseconds = milliseconds
+                .parse::<u64>()
+                .map_err(|_| ParseErrorKind::InvalidArgument(milliseconds.to_string()))?;
+            Ok(Statement::Action(Action::Wait(Duration::from_millis(
+                milliseconds,
+            ))))
+        }
+        name => Err(ParseErrorKind::UnknownStatement(name.to_string())),
+    }
+}
+
+fn expect_arguments<'a, const N: usize>(
+    arguments: &[&'a str],
+) -> Result<[&'a str; N], ParseErrorKind> {
+    arguments
+        .try_into()
+        .map_err(|_| ParseErrorKind::ArgumentCount {
+            expected: N,
+            found: arguments.len(),
+        })
+}
+
+/// Reads a motion's `(amount, speed)`, where the amount is a finite number and the speed is a
+/// percentage above zero.
+fn motion_arguments(arguments: &[&str]) -> Result<[f64; 2], ParseErrorKind> {
+    let [amount, speed] = expect_arguments(arguments)?;
+    let number = |argument: &str, valid: fn(f64) -> bool| {
+        argument
+            .parse::<f64>()
+            .ok()
+            .filter(|value| valid(*value))
+            .ok_or_else(|| ParseErrorKind::InvalidArgument(argument.to_string()))
+    };
+
+    Ok([
+        number(amount, f64::is_finite)?,
+        number(speed, |speed| speed > 0.0 && speed <= 100.0)?,
+    ])
+}
+
+/// When a [`Hook`] runs at its end point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookTime {
+    /// `at <uid>:`, once the drive to the end point finishes.
+    Arrive,
+
+    /// `leaving <uid>:`, just before the drive away from the end point.
+    Leave,
+}
+
+/// Actions from an actions file to run at one of the path's end points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    /// The line of the hook's `at` or `leaving` line, for errors.
+    pub line: usize,
+
+    pub time: HookTime,
+
+    /// The JerryIO uid of the end point.
+    pub uid: String,
+
+    pub actions: Vec<Action>,
+}
+
+/// Parses every hook in an actions file.
+pub fn parse_actions(text: &str) -> Result<Vec<Hook>, ParseError> {
+    let mut hooks: Vec<Hook> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |kind| ParseError {
+            line: line_number,
+            kind,
+        };
+        let line = match line.split_once("//") {
+            Some((code, _comment)) => code,
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_suffix(':') {
+            let (time, uid) = match header.split_once(char::is_whitespace) {
+                Some(("at", uid)) => (HookTime::Arrive, uid.trim()),
+                Some(("leaving", uid)) => (HookTime::Leave, uid.trim()),
+                _ => return Err(error(ParseErrorKind::Syntax)),
+            };
+            hooks.push(Hook {
+                line: line_number,
+                time,
+                uid: uid.to_string(),
+                actions: Vec::new(),
+            });
+            continue;
+        }
+
+        let action = match parse_statement(line).map_err(error)? {
+            Statement::Action(action) => action,
+            _ => {
+                let name = line.split_once('(').map_or(line, |(name, _)| name.trim());
+                return Err(error(ParseErrorKind::NotAnAction(name.to_string())));
+            }
+        };
+        hooks
+            .last_mut()
+            .ok_or_else(|| error(ParseErrorKind::MissingEndPoint))?
+            .actions
+            .push(action);
+    }
+
+    Ok(hooks)
+}
+
+/// The paths in a route file's JerryIO data, with every length converted to inches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathData {
+    pub robot_width: f64,
+    pub robot_height: f64,
+    pub paths: Vec<Path>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub name: String,
+
+    /// The speed the path's motions were generated with, as a percentage of full power.
+    pub speed: Option<f64>,
+
+    pub segments: Vec<Segment>,
+}
+
+/// A piece of a path between two end points. Straight segments only have the two end points, and
+/// curves have bezier control points between them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub controls: Vec<Control>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Control {
+    /// The id JerryIO gave the point, which it keeps when the path is edited.
+    pub uid: String,
+
+    pub x: f64,
+    pub y: f64,
+
+    /// The absolute heading in degrees the robot should have at this point, for end points,
+    /// converted from a bearing (see [`from_bearing`]).
+    pub heading: Option<f64>,
+
+    pub end_point: bool,
+}
+
+impl Path {
+    /// Every end point the path drives through in order, starting with where it starts.
+    pub fn waypoints(&self) -> Vec<Control> {
+        let start = self
+            .segments
+            .first()
+            .and_then(|segment| segment.controls.first());
+        let ends = self
+            .segments
+            .iter()
+            .filter_map(|segment| segment.controls.last());
+
+        start.into_iter().chain(ends).cloned().collect()
+    }
+}
+
+/// Why a route file's path data couldn't be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataError {
+    /// There's no `#PATH.JERRYIO-DATA` line.
+    Missing,
+
+    /// The data isn't valid JSON.
+    Json(String),
+
+    /// The path was drawn in a coordinate system other than [`COORDINATE_SYSTEM`], so its
+    /// headings can't be converted.
+    CoordinateSystem(String),
+
+    /// The JSON is missing something, or has it as the wrong type. Holds where in the JSON it was
+    /// expected, like `paths[0].segments[2].controls[1].x`.
+    Field(String),
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no `{DATA_MARKER}` line"),
+            Self::Json(e) => write!(f, "path data isn't valid json: {e}"),
+            Self::CoordinateSystem(system) => write!(
+                f,
+                "path was drawn in `{system}` coordinates, not `{COORDINATE_SYSTEM}`"
+            ),
+            Self::Field(field) => {
+                write!(f, "path data is missing `{field}` or it's the wrong type")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+/// Reads the JerryIO path data at the end of a route file.
+pub fn parse_data(text: &str) -> Result<PathData, DataError> {
+    let json = text
+        .lines()
+        .find_map(|line| line.trim_start().strip_prefix(DATA_MARKER))
+        .ok_or(DataError::Missing)?;
+    let root = json::parse(json).map_err(DataError::Json)?;
+
+    // JerryIO's unit of length, in centimeters
+    let gc = root.get("gc");
+    let coordinate_system = gc
+        .and_then(|gc| gc.get("coordinateSystem"))
+        .and_then(Value::as_str)
+        .ok_or_else(|| DataError::Field("gc.coordinateSystem".to_string()))?;
+    if coordinate_system != COORDINATE_SYSTEM {
+        return Err(DataError::CoordinateSystem(coordinate_system.to_string()));
+    }
+
+    let uol = number(gc.and_then(|gc| gc.get("uol")), || "gc.uol".to_string())?;
+    let inches = |value: f64| value * uol / 2.54;
+
+    let robot_width = inches(number(gc.and_then(|gc| gc.get("robotWidth")), || {
+        "gc.robotWidth".to_string()
+    })?);
+    let robot_height = inches(number(gc.and_then(|gc| gc.get("robotHeight")), || {
+        "gc.robotHeight".to_string()
+    })?);
+
+    let mut paths = Vec::new();
+    for (i, path) in array(root.get("paths"), || "paths".to_string())?
+        .iter()
+        .enumerate()
+    {
+        let mut segments = Vec::new();
+        for (j, segment) in array(path.get("segments"), || format!("paths[{i}].segments"))?
+            .iter()
+            .enumerate()
+        {
+            let controls = array(segment.get("controls"), || {
+                format!("paths[{i}].segments[{j}].controls")
+            })?;
+
+            let mut converted = Vec::new();
+            for (k, control) in controls.iter().enumerate() {
+                let field = |name: &str| format!("paths[{i}].segments[{j}].controls[{k}].{name}");
+                converted.push(Control {
+                    uid: control
+                        .get("uid")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| DataError::Field(field("uid")))?
+                        .to_string(),
+                    x: inches(number(control.get("x"), || field("x"))?),
+                    y: inches(number(control.get("y"), || field("y"))?),
+                    heading: match control.get("heading") {
+                        Some(heading) => {
+                            Some(from_bearing(number(Some(heading), || field("heading"))?))
+                        }
+                        None => None,
+                    },
+                    end_point: control.get("__type").and_then(Value::as_str) == Some("end-point"),
+                });
+            }
+            segments.push(Segment {
+                controls: converted,
+            });
+        }
+
+        paths.push(Path {
+            name: path
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            speed: path
+                .get("pc")
+                .and_then(|pc| pc.get("speed"))
+                .and_then(Value::as_f64),
+            segments,
+        });
+    }
+
+    Ok(PathData {
+        robot_width,
+        robot_height,
+        paths,
+    })
+}
+
+fn number(value: Option<&Value>, field: impl FnOnce() -> String) -> Result<f64, DataError> {
+    value
+        .and_then(Value::as_f64)
+        .filter(|value| value.is_finite())
+        .ok_or_else(|| DataError::Field(field()))
+}
+
+fn array(value: Option<&Value>, field: impl FnOnce() -> String) -> Result<&[Value], DataError> {
+    value
+        .and_then(Value::as_array)
+        .ok_or_else(|| DataError::Field(field()))
+}
+
+/// A route file that parsed and stays on the field, so it's ready to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub statements: Vec<Statement>,
+    pub data: PathData,
+}
+
+impl Route {
+    /// Where the route's first path starts.
+    pub fn start(&self) -> Option<Control> {
+        self.data
+            .paths
+            .first()
+            .and_then(|path| path.waypoints().first().cloned())
+    }
+
+    /// Where the route's first path starts as `(x, y, heading)`, with the heading already converted
+    /// from a bearing. A start without a heading faces along +x.
+    ///
+    /// This is the pose the robot is placed at, so it's what start verification, relocalization
+    /// and the localizer all start from.
+    pub fn start_pose(&self) -> Option<(f64, f64, f64)> {
+        self.start()
+            .map(|start| (start.x, start.y, start.heading.unwrap_or_default()))
+    }
+
+    /// The end points of the route's first path.
+    pub fn waypoints(&self) -> Vec<Control> {
+        self.data
+            .paths
+            .first()
+            .map(Path::waypoints)
+            .unwrap_or_default()
+    }
+}
+
+/// Why a route file can't be run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteError {
+    Parse(ParseError),
+    Data(DataError),
+    Actions(ParseError),
+
+    /// The route file doesn't have one drive per segment of its path, so hooks can't be matched
+    /// to end points. This happens when the path is edited without exporting it again.
+    SegmentCount {
+        drives: usize,
+        segments: usize,
+    },
+
+    /// A control point is off the field. Holds where it is in the path data and where it is on the
+    /// field.
+    OffField {
+        path: usize,
+        segment: usize,
+        control: usize,
+        x: f64,
+        y: f64,
+    },
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "{e}"),
+            Self::Data(e) => write!(f, "{e}"),
+            Self::Actions(e) => write!(f, "actions {e}"),
+            Self::SegmentCount { drives, segments } => write!(
+                f,
+                "{drives} drives for {segments} path segments, export the path again"
+            ),
+            Self::OffField {
+                path,
+                segment,
+                control,
+                x,
+                y,
+            } => write!(
+                f,
+                "paths[{path}].segments[{segment}].controls[{control}] at ({x}, {y}) is off the field"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Parses a whole route file and checks that every point it drives through is on the field, then
+/// puts the hooks from its actions file, if it has one, in between its motions.
+pub fn load(text: &str, actions: Option<&str>) -> Result<Route, RouteError> {
+    let statements = parse(text).map_err(RouteError::Parse)?;
+    let data = parse_data(text).map_err(RouteError::Data)?;
+
+    for (i, path) in data.paths.iter().enumerate() {
+        for (j, segment) in path.segments.iter().enumerate() {
+            for (k, control) in segment.controls.iter().enumerate() {
+                if control.x.abs() > FIELD_HALF_WIDTH || control.y.abs() > FIELD_HALF_WIDTH {
+                    return Err(RouteError::OffField {
+                        path: i,
+                        segment: j,
+                        control: k,
+                        x: control.x,
+                        y: control.y,
+                    });
+                }
+            }
+        }
+    }
+
+    let mut route = Route { statements, data };
+    if let Some(actions) = actions {
+        let hooks = parse_actions(actions).map_err(RouteError::Actions)?;
+        route.statements = insert_hooks(&route.statements, &route.waypoints(), &hooks)?;
+    }
+
+    Ok(route)
+}
+
+/// Puts each hook's actions between the drives to and from its end point. JerryIO generates one
+/// drive per segment, so the drives count through the path's end points.
+fn insert_hooks(
+    statements: &[Statement],
+    waypoints: &[Control],
+    hooks: &[Hook],
+) -> Result<Vec<Statement>, RouteError> {
+    let is_drive = |statement: &&Statement| {
+        matches!(
+            statement,
+            Statement::Forward { .. } | Statement::Backward { .. }
+        )
+    };
+    let drives = statements.iter().filter(is_drive).count();
+    let segments = waypoints.len().saturating_sub(1);
+    if drives != segments {
+        return Err(RouteError::SegmentCount { drives, segments });
+    }
+
+    for hook in hooks {
+        let error = |kind| {
+            RouteError::Actions(ParseError {
+                line: hook.line,
+                kind,
+            })
+        };
+        let index = waypoints
+            .iter()
+            .position(|waypoint| waypoint.uid == hook.uid)
+            .ok_or_else(|| error(ParseErrorKind::UnknownEndPoint(hook.uid.clone())))?;
+        match hook.time {
+            HookTime::Arrive if index == 0 => {
+                return Err(error(ParseErrorKind::NeverRuns(format!("at {}", hook.uid))));
+            }
+            HookTime::Leave if index == segments => {
+                return Err(error(ParseErrorKind::NeverRuns(format!(
+                    "leaving {}",
+                    hook.uid
+                ))));
+            }
+            _ => {}
+        }
+    }
+
+    let run_hooks = |with_hooks: &mut Vec<Statement>, time: HookTime, waypoint: &Control| {
+        for hook in hooks
+            .iter()
+            .filter(|hook| hook.time == time && hook.uid == waypoint.uid)
+        {
+            with_hooks.extend(hook.actions.iter().copied().map(Statement::Action));
+        }
+    };
+
+    let mut with_hooks = Vec::new();
+    let mut point = 0;
+    for statement in statements {
+        if is_drive(&statement) {
+            run_hooks(&mut with_hooks, HookTime::Leave, &waypoints[point]);
+            with_hooks.push(*statement);
+            point += 1;
+            run_hooks(&mut with_hooks, HookTime::Arrive, &waypoints[point]);
+        } else {
+            with_hooks.push(*statement);
+        }
+    }
+
+    Ok(with_hooks)
+}
+
+/// Just enough JSON to read JerryIO's path data.
+mod json {
+    /// How deep arrays and objects can be nested. JerryIO's data only goes a few levels deep, and
+    /// this keeps a broken file from recursing until the stack runs out.
+    const MAX_DEPTH: usize = 32;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum Value {
+        Null,
+        Bool(bool),
+        Number(f64),
+        String(String),
+        Array(Vec<Value>),
+        Object(Vec<(String, Value)>),
+    }
+
+    impl Value {
+        pub fn get(&self, key: &str) -> Option<&Value> {
+            match self {
+                Self::Object(members) => members
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, value)| value),
+                _ => None,
+            }
+        }
+
+        pub fn as_f64(&self) -> Option<f64> {
+            match self {
+                Self::Number(number) => Some(*number),
+                _ => None,
+            }
+        }
+
+        pub fn as_str(&self) -> Option<&str> {
+            match self {
+                Self::String(string) => Some(string),
+                _ => None,
+            }
+        }
+
+        pub fn as_array(&self) -> Option<&[Value]> {
+            match self {
+                Self::Array(values) => Some(values),
+                _ => None,
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Value, String> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.position != text.len() {
+            return Err(parser.error("trailing characters"));
+        }
+        Ok(value)
+    }
+
+    struct Parser<'a> {
+        text: &'a str,
+        position: usize,
+        depth: usize,
+    }
+
+    impl Parser<'_> {
+        fn error(&self, message: &str) -> String {
+            format!("{message} at byte {}", self.position)
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.text[self.position..].chars().next()
+        }
+
+        fn next(&mut self) -> Option<char> {
+            let c = self.peek()?;
+            self.position += c.len_utf8();
+            Some(c)
+        }
+
+        fn skip_whitespace(&mut self) {
+            while self.peek().is_some_and(char::is_whitespace) {
+                self.next();
+            }
+        }
+
+        fn expect(&mut self, expected: char) -> Result<(), String> {
+            self.skip_whitespace();
+            if self.next() == Some(expected) {
+                Ok(())
+            } else {
+                Err(self.error(&format!("expected `{expected}`")))
+            }
+        }
+
+        fn value(&mut self) -> Result<Value, String> {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(c @ ('{' | '[')) => {
+                    if self.depth == MAX_DEPTH {
+                        return Err(self.error("nested too deeply"));
+                    }
+                    self.depth += 1;
+                    let value = if c == '{' {
+                        self.object()
+                    } else {
+                        self.array()
+                    };
+                    self.depth -= 1;
+                    value
+                }
+                Some('"') => self.string().map(Value::String),
+                Some('t') => self.literal("true", Value::Bool(true)),
+                Some('f') => self.literal("false", Value::Bool(false)),
+                Some('n') => self.literal("null", Value::Null),
+                Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+                _ => Err(self.error("expected a value")),
+            }
+        }
+
+        fn literal(&mut self, literal: &str, value: Value) -> Result<Value, String> {
+            if self.text[self.position..].starts_with(literal) {
+                self.position += literal.len();
+                Ok(value)
+            } else {
+                Err(self.error("expected a value"))
+            }
+        }
+
+        fn number(&mut self) -> Result<Value, String> {
+            let start = self.position;
+            while self
+                .peek()
+                .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            {
+                self.next();
+            }
+            self.text[start..self.position]
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| self.error("invalid number"))
+        }
+
+        fn string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut string = String::new();
+            loop {
+                match self.next() {
+                    Some('"') => return Ok(string),
+                    Some('\\') => {
+                        let escaped = match self.next() {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some('b') => '\u{8}',
+                            Some('f') => '\u{c}',
+                            Some('u') => {
+                                let hex = self
+                                    .text
+                                    .get(self.position..self.position + 4)
+                                    .ok_or_else(|| self.error("invalid escape"))?;
+                                self.position += 4;
+                                // surrogate pairs aren't needed for anything JerryIO writes
+                                u32::from_str_radix(hex, 16)
+                                    .ok()
+                                    .map(|code| {
+                                        char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                                    })
+                                    .ok_or_else(|| self.error("invalid escape"))?
+                            }
+                            Some(c @ ('"' | '\\' | '/')) => c,
+                            _ => return Err(self.error("invalid escape")),
+                        };
+                        string.push(escaped);
+                    }
+                    Some(c) => string.push(c),
+                    None => return Err(self.error("unterminated string")),
+                }
+            }
+        }
+
+        fn array(&mut self) -> Result<Value, String> {
+            self.expect('[')?;
+            let mut values = Vec::new();
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.next();
+                return Ok(Value::Array(values));
+            }
+
+            loop {
+                values.push(self.value()?);
+                self.skip_whitespace();
+                match self.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Value::Array(values)),
+                    _ => return Err(self.error("expected `,` or `]`")),
+                }
+            }
+        }
+
+        fn object(&mut self) -> Result<Value, String> {
+            self.expect('{')?;
+            let mut members = Vec::new();
+            self.skip_whitespace();
+            if self.peek() == Some('}') {
+                self.next();
+                return Ok(Value::Object(members));
+            }
+
+            loop {
+                self.skip_whitespace();
+                let name = self.string()?;
+                self.expect(':')?;
+                members.push((name, self.value()?));
+                self.skip_whitespace();
+                match self.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Value::Object(members)),
+                    _ => return Err(self.error("expected `,` or `}`")),
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn strings_and_escapes() {
+            assert_eq!(parse(r#""path""#), Ok(Value::String("path".to_string())));
+            assert_eq!(parse(r#""""#), Ok(Value::String(String::new())));
+            assert_eq!(
+                parse(r#""a\"b\\c\/d\n\t\r\b\f""#),
+                Ok(Value::String("a\"b\\c/d\n\t\r\u{8}\u{c}".to_string()))
+            );
+            assert_eq!(
+                parse(r#""\u00b0 and \u00E9""#),
+                Ok(Value::String("\u{b0} and \u{e9}".to_string()))
+            );
+            assert_eq!(parse(r#""°""#), Ok(Value::String("°".to_string())));
+            assert!(parse(r#""\x""#).is_err());
+            assert!(parse(r#""\u12zz""#).is_err());
+        }
+
+        #[test]
+        fn numbers() {
+            assert_eq!(parse("0"), Ok(Value::Number(0.0)));
+            assert_eq!(parse("-61"), Ok(Value::Number(-61.0)));
+            assert_eq!(
+                parse("79.95565632579175"),
+                Ok(Value::Number(79.95565632579175))
+            );
+            assert_eq!(parse("2.54e-2"), Ok(Value::Number(0.0254)));
+            assert_eq!(parse("1E3"), Ok(Value::Number(1000.0)));
+            assert!(parse("1.2.3").is_err());
+            assert!(parse("-").is_err());
+            assert!(parse(".5").is_err());
+        }
+
+        #[test]
+        fn literals() {
+            assert_eq!(parse("true"), Ok(Value::Bool(true)));
+            assert_eq!(parse("false"), Ok(Value::Bool(false)));
+            assert_eq!(parse("null"), Ok(Value::Null));
+            assert!(parse("nul").is_err());
+            assert!(parse("yes").is_err());
+        }
+
+        #[test]
+        fn nested_arrays_and_objects() {
+            let value =
+                parse(r#" { "paths": [ { "x": 1, "controls": [[], {}] } ], "lock": false } "#)
+                    .unwrap();
+
+            let paths = value.get("paths").and_then(Value::as_array).unwrap();
+            assert_eq!(paths.len(), 1);
+            assert_eq!(paths[0].get("x").and_then(Value::as_f64), Some(1.0));
+            assert_eq!(
+                paths[0].get("controls"),
+                Some(&Value::Array(vec![
+                    Value::Array(Vec::new()),
+                    Value::Object(Vec::new())
+                ]))
+            );
+            assert_eq!(value.get("lock"), Some(&Value::Bool(false)));
+            assert_eq!(value.get("missing"), None);
+        }
+
+        #[test]
+        fn truncated_input() {
+            for text in [
+                "",
+                "[",
+                "[1,",
+                "[1, 2",
+                "{",
+                r#"{"x""#,
+                r#"{"x":"#,
+                r#"{"x": 1"#,
+                r#"{"x": 1,"#,
+                r#""unterminated"#,
+                r#""\"#,
+                r#""\u00"#,
+                "tru",
+            ] {
+                assert!(parse(text).is_err(), "{text:?} parsed");
+            }
+        }
+
+        #[test]
+        fn trailing_characters() {
+            assert!(parse("[] []").is_err());
+            assert!(parse("{},").is_err());
+            assert!(parse("[1,]").is_err());
+            assert!(parse(r#"{"x": 1,}"#).is_err());
+        }
+
+        #[test]
+        fn nesting_is_limited() {
+            let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+            assert!(parse(&nested(MAX_DEPTH)).is_ok());
+            assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+
+            let objects = r#"{"a":"#.repeat(MAX_DEPTH + 1) + "1" + &"}".repeat(MAX_DEPTH + 1);
+            assert!(parse(&objects).is_err());
+
+            // deep enough to overflow the stack without the limit
+            assert!(parse(&"[".repeat(1_000_000)).is_err());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SKILLS: &str = include_str!("../routes/skills-route.txt");
+    const SKILLS_ACTIONS: &str = include_str!("../routes/skills-route.actions");
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn bearings_become_counterclockwise_headings() {
+        assert_close(from_bearing(0.0), 90.0);
+        assert_close(from_bearing(90.0), 0.0);
+        assert_close(from_bearing(180.0), 270.0);
+        assert_close(from_bearing(270.0), 180.0);
+        assert_close(from_bearing(-45.0), 135.0);
+    }
+
+    #[test]
+    fn skills_route_starts_facing_negative_x() {
+        let route = load(SKILLS, Some(SKILLS_ACTIONS)).unwrap();
+        let (x, y, heading) = route.start_pose().unwrap();
+
+        // JerryIO has the start at a bearing of 270°, backing up towards +x
+        assert_close(x, -61.0);
+        assert_close(y, 18.5);
+        assert_close(heading, 180.0);
+    }
+
+    #[test]
+    fn skills_route_turns_face_along_the_path() {
+        let route = load(SKILLS, Some(SKILLS_ACTIONS)).unwrap();
+        let waypoints = route.waypoints();
+
+        // backward(16, 30); then turnTo(79.956, 30); to face along the second segment
+        let heading = route
+            .statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::TurnTo { heading, .. } => Some(*heading),
+                _ => None,
+            })
+            .unwrap();
+        let (from, to) = (&waypoints[1], &waypoints[2]);
+        let along = (to.y - from.y).atan2(to.x - from.x).to_degrees();
+
+        assert_close(heading, 10.044);
+        assert_close(heading, along);
+        assert_close(from.heading.unwrap(), heading);
+    }
+
+    #[test]
+    fn hooks_run_between_the_right_motions() {
+        let statements = load(SKILLS, Some(SKILLS_ACTIONS)).unwrap().statements;
+        let position = |statement: Statement| {
+            statements
+                .iter()
+                .position(|&other| other == statement)
+                .unwrap()
+        };
+
+        // leaving the start
+        assert_eq!(
+            statements[..3],
+            [
+                Statement::Action(Action::DisableColorSort),
+                Statement::Action(Action::Intake(IntakeAction::Collect)),
+                Statement::Backward {
+                    distance: 16.0,
+                    speed: 30.0
+                },
+            ]
+        );
+
+        // at the middle goal, before turning away from it
+        let middle = position(Statement::Backward {
+            distance: 13.924,
+            speed: 30.0,
+        });
+        assert_eq!(
+            statements[middle + 1],
+            Statement::Action(Action::Intake(IntakeAction::ScoreMiddle))
+        );
+
+        // leaving for the loader, after turning towards it
+        let loader = position(Statement::Backward {
+            distance: 7.793,
+            speed: 30.0,
+        });
+        assert_eq!(
+            statements[loader - 1],
+            Statement::Action(Action::ExtendMatchloader)
+        );
+        assert!(matches!(statements[loader - 2], Statement::TurnTo { .. }));
+
+        let actions = statements
+            .iter()
+            .filter(|statement| matches!(statement, Statement::Action(_)))
+            .count();
+        assert_eq!(actions, 22);
+    }
+
+    #[test]
+    fn broken_actions_are_rejected() {
+        let error = |actions: &str| match load(SKILLS, Some(actions)) {
+            Err(RouteError::Actions(e)) => e,
+            result => panic!("expected an actions error, got {result:?}"),
+        };
+
+        assert_eq!(
+            error("intake(collect);"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::MissingEndPoint
+            }
+        );
+        assert_eq!(
+            error("at 5HnU8mPuWK:\nforward(10, 50);"),
+            ParseError {
+                line: 2,
+                kind: ParseErrorKind::NotAnAction("forward".to_string())
+            }
+        );
+        assert_eq!(
+            error("// hooks\nnear 5HnU8mPuWK:"),
+            ParseError {
+                line: 2,
+                kind: ParseErrorKind::Syntax
+            }
+        );
+        assert_eq!(
+            error("at 5HnU8mPuWK:\nwait(1);\n\nat missing:\nwait(1);"),
+            ParseError {
+                line: 4,
+                kind: ParseErrorKind::UnknownEndPoint("missing".to_string())
+            }
+        );
+        assert_eq!(
+            error("at CX0CEHVZSi:\nwait(1);"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::NeverRuns("at CX0CEHVZSi".to_string())
+            }
+        );
+        assert_eq!(
+            error("leaving ymXnngLKeV:\nwait(1);"),
+            ParseError {
+                line: 1,
+                kind: ParseErrorKind::NeverRuns("leaving ymXnngLKeV".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn hooks_need_one_drive_per_segment() {
+        let text = SKILLS.replacen("backward(16, 30);\n", "", 1);
+        assert_eq!(
+            load(&text, Some(SKILLS_ACTIONS)),
+            Err(RouteError::SegmentCount {
+                drives: 22,
+                segments: 23
+            })
+        );
+
+        // without hooks the drives don't have to line up with anything
+        assert!(load(&text, None).is_ok());
+    }
+
+    #[test]
+    fn relative_turns_are_rejected() {
+        for name in ["turnLeft", "turnRight"] {
+            assert_eq!(
+                parse(&format!("forward(10, 50);\n{name}(90, 50);")),
+                Err(ParseError {
+                    line: 2,
+                    kind: ParseErrorKind::RelativeTurn(name.to_string()),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn other_coordinate_systems_are_rejected() {
+        let text = SKILLS.replace(COORDINATE_SYSTEM, "Cartesian");
+        assert_eq!(
+            parse_data(&text),
+            Err(DataError::CoordinateSystem("Cartesian".to_string()))
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use evian::prelude::*;
+use log::info;
+use vexide::{prelude::*, task::Task};
+
+use crate::{
+    ekf::{Pose, wrap},
+    fused_tracking::{FusedTracking, TrackingReader},
+    mcl::{Beam, MAX_RANGE, Mcl},
+    relocalize::DistanceSensors,
+};
+
+/// How often the particle filter is updated while it's running.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Particle Filter Localization
+///
+/// Runs an [`Mcl`] in the background during a route, moving its particles by the tracked pose and
+/// weighing them with the distance sensors. The route decides when to trust it by calling
+/// [`Localizer::apply`], which moves tracking onto the filter's estimate once the particles have
+/// agreed on one.
+///
+/// Any reset of the tracked pose (from this, wall relocalization, or placing the robot at the start
+/// of a route) is a jump rather than motion, so the particles don't follow it.
+///
+/// Without any distance sensors there's nothing to weigh the particles with, so the filter never
+/// starts.
+pub struct Localizer {
+    state: Rc<RefCell<LocalizerState>>,
+    _task: Task<()>,
+
+    // whether there are any distance sensors to weigh the particles with
+    enabled: bool,
+
+    /// Spread (in inches) the particles are scattered by when the filter is started.
+    pub start_spread: f64,
+
+    /// Largest particle spread (in inches) at which the estimate is trusted.
+    pub max_spread: f64,
+}
+
+struct LocalizerState {
+    mcl: Mcl,
+    reader: TrackingReader,
+    running: bool,
+
+    // tracked pose and reset count at the last update, used to find how far the robot moved since
+    prev_pose: Pose,
+    prev_resets: u32,
+}
+
+impl Localizer {
+    /// Constructs a stopped [`Localizer`].
+    ///
+    /// The particles in `mcl` are reseeded every time the localizer is started, so it can be
+    /// constructed around any pose.
+    pub fn new(
+        mcl: Mcl,
+        sensors: DistanceSensors,
+        reader: TrackingReader,
+        start_spread: f64,
+        max_spread: f64,
+    ) -> Self {
+        let state = Rc::new(RefCell::new(LocalizerState {
+            mcl,
+            reader,
+            running: false,
+            prev_pose: Pose::default(),
+            prev_resets: 0,
+        }));
+
+        Self {
+            enabled: !sensors.is_empty(),
+            _task: spawn(Self::task(state.clone(), sensors)),
+            state,
+            start_spread,
+            max_spread,
+        }
+    }
+
+    async fn task(state: Rc<RefCell<LocalizerState>>, sensors: DistanceSensors) {
+        loop {
+            {
+                let mut state = state.borrow_mut();
+                if state.running {
+                    state.predict();
+
+                    let readings = sensors
+                        .iter()
+                        .filter_map(|sensor| {
+                            let distance = sensor.distance().filter(|d| *d < MAX_RANGE)?;
+                            let beam = Beam {
+                                x: sensor.mount.offset.x,
+                                y: sensor.mount.offset.y,
+                                angle: sensor.mount.angle.as_radians(),
+                            };
+                            Some((beam, distance))
+                        })
+                        .collect::<Vec<_>>();
+                    state.mcl.update(&readings);
+                }
+            }
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+    }
+
+    /// Starts the filter with particles scattered around the currently tracked pose.
+    pub fn start(&mut self, tracking: &FusedTracking) {
+        if !self.enabled {
+            return;
+        }
+
+        let pose = Pose {
+            x: tracking.position().x,
+            y: tracking.position().y,
+            heading: tracking.heading().wrapped().as_radians(),
+        };
+
+        let mut state = self.state.borrow_mut();
+        state.mcl.reset(pose, self.start_spread);
+        state.prev_pose = pose;
+        state.prev_resets = state.reader.resets();
+        state.running = true;
+    }
+
+    /// Stops updating the filter.
+    pub fn stop(&mut self) {
+        self.state.borrow_mut().running = false;
+    }
+
+    /// Moves the tracked position onto the filter's estimate, if the particles agree closely
+    /// enough on one.
+    ///
+    /// Returns `true` if the position was corrected.
+    pub fn apply(&mut self, tracking: &mut FusedTracking) -> bool {
+        let mut state = self.state.borrow_mut();
+        if !state.running || state.mcl.spread() > self.max_spread {
+            return false;
+        }
+
+        let estimate = state.mcl.estimate();
+        info!(
+            "localized from ({:.2}, {:.2}) to ({:.2}, {:.2})",
+            tracking.position().x,
+            tracking.position().y,
+            estimate.x,
+            estimate.y
+        );
+        tracking.set_position((estimate.x, estimate.y));
+        true
+    }
+}
+
+impl LocalizerState {
+    fn predict(&mut self) {
+        let pose = self.reader.pose();
+
+        // the pose was moved since the last update, so start following it from where it is now.
+        // this drops any real motion since the last update, which is small enough for the
+        // particles' noise to cover.
+        let resets = self.reader.resets();
+        if resets != self.prev_resets {
+            self.prev_pose = pose;
+            self.prev_resets = resets;
+            return;
+        }
+
+        // express the motion relative to where the robot was facing, since the particles each have
+        // their own idea of which way that is
+        let (dx, dy) = (pose.x - self.prev_pose.x, pose.y - self.prev_pose.y);
+        let (sin, cos) = self.prev_pose.heading.sin_cos();
+        let forward = dx * cos + dy * sin;
+        let sideways = -dx * sin + dy * cos;
+        let turn = wrap(pose.heading - self.prev_pose.heading);
+
+        self.mcl.predict(forward, sideways, turn);
+        self.prev_pose = pose;
+    }
+}
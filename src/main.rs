@@ -1,25 +1,55 @@
 mod auton;
 mod banner;
+mod boomerang;
+mod calibration;
 mod consts;
 mod curvature;
+mod defense;
+mod drive_config;
+mod drive_motors;
+mod ekf;
+mod field;
+mod field_centric;
+mod fused_tracking;
+mod heading_hold;
+mod imu;
 mod intake;
+mod jerryio;
+mod localizer;
 mod logger;
 mod matchloader;
+mod mcl;
+mod motion;
+mod motion_monitor;
+mod path;
+mod placement;
+mod ports;
+mod profile;
+mod profiled;
+mod pure_pursuit;
+mod ramsete;
+mod relocalize;
+mod rng;
+mod robot_config;
+mod route;
+mod steering;
+mod tip_guard;
+mod trace;
+mod trace_recorder;
+mod tracking_config;
+mod traction;
+mod trajectory;
+mod watchdog;
 mod wing;
 
 use std::{cell::Cell, rc::Rc, time::Duration};
 
-use autons::{prelude::*, route, simple::SimpleSelect};
+use autons::{prelude::*, simple::SimpleSelect};
 use evian::{
-    drivetrain::model::Differential,
-    math::Angle,
+    drivetrain::model::{Arcade, Differential},
     prelude::*,
-    tracking::{
-        shared_motors,
-        wheeled::{TrackingWheel, WheeledTracking},
-    },
 };
-use log::{LevelFilter, warn};
+use log::{LevelFilter, info, warn};
 use vexide::{
     display::{Rect, TouchState},
     prelude::*,
@@ -30,9 +60,30 @@ use vexide::{
 use crate::{
     banner::THEME_RAINBOTS,
     curvature::CurvatureDrive,
+    defense::Defense,
+    drive_motors::{DriveMotors, next_brake_mode},
+    field_centric::FieldCentric,
+    fused_tracking::{FusedTracking, GpsSource, HeadingFallback},
+    heading_hold::HeadingHold,
+    imu::SharedImu,
     intake::{Command, CommandCell, Intake},
+    localizer::Localizer,
     logger::RobotLogger,
     matchloader::Matchloader,
+    mcl::Mcl,
+    motion::Motion,
+    motion_monitor::MotionMonitor,
+    placement::{self, PlacementCheck, placed_route},
+    pure_pursuit::PurePursuit,
+    ramsete::Ramsete,
+    relocalize::{self, Relocalizer},
+    robot_config::RobotConfig,
+    route::SdRoute,
+    tip_guard::{Tilt, TipGuard},
+    trace_recorder::TraceRecorder,
+    tracking_config::OdometrySource,
+    traction::TractionControl,
+    watchdog::Watchdog,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -42,11 +93,25 @@ enum Alliance {
 }
 
 struct Jodio {
-    dt: Drivetrain<Differential, WheeledTracking>,
+    dt: Drivetrain<TipGuard<TractionControl<Differential>>, FusedTracking>,
     _intake_task: Task<()>,
     intake_command: CommandCell,
     curvature: CurvatureDrive,
+    heading_hold: HeadingHold,
+    field_centric: FieldCentric,
+    defense: Defense,
+    drive_motors: DriveMotors,
+    brake_mode: BrakeMode,
     matchloader: Matchloader,
+    relocalizer: Relocalizer,
+    localizer: Localizer,
+    trace: TraceRecorder,
+    monitor: MotionMonitor,
+    motion: Motion,
+    config: RobotConfig,
+    odometry: OdometrySource,
+    calibration_imu: Option<Rc<InertialSensor>>,
+    sd_routes: Vec<SdRoute>,
     ctrl: Controller,
     allegiance: Rc<Cell<Option<Alliance>>>,
 }
@@ -54,11 +119,107 @@ struct Jodio {
 impl SelectCompete for Jodio {
     async fn driver(&mut self) {
         let mut collecting = false;
+        let mut was_tipping = false;
+        // brake mode the drivetrain was last stopped with, `None` while it's being driven
+        let mut braked = None;
         loop {
             let state = self.ctrl.state().unwrap_or_default();
-            self.curvature
-                .update(&mut self.dt, state.left_stick.y(), state.right_stick.x())
-                .unwrap_or_else(|e| warn!("couldn't drive drivetrain: {e}"));
+            let throttle = state.left_stick.y();
+            let turn = state.right_stick.x();
+
+            // A => Toggle Heading Hold
+            if state.button_a.is_now_pressed() {
+                self.heading_hold.toggle();
+                info!("heading hold enabled: {}", self.heading_hold.enabled);
+            }
+
+            // Y => Toggle Field-Centric Drive
+            if state.button_y.is_now_pressed() {
+                self.field_centric.toggle();
+                info!("field-centric enabled: {}", self.field_centric.enabled);
+            }
+
+            // B => Cycle Brake Mode
+            if state.button_b.is_now_pressed() {
+                self.brake_mode = next_brake_mode(self.brake_mode);
+                info!("brake mode: {:?}", self.brake_mode);
+            }
+
+            let position = self.dt.tracking.position();
+            let heading = self.dt.tracking.heading();
+
+            // Down + Left => Calibrate Wheel Diameter
+            // Down + Right => Calibrate Track Width
+            // the controller is ignored during autonomous, so these can only run from here
+            if state.button_down.is_pressed() {
+                if state.button_left.is_now_pressed() {
+                    calibration::wheel_diameter(self).await;
+                    braked = None;
+                    continue;
+                }
+                if state.button_right.is_now_pressed() {
+                    calibration::track_width(self).await;
+                    braked = None;
+                    continue;
+                }
+            }
+
+            // X => Toggle Defense
+            if state.button_x.is_now_pressed() {
+                if self.defense.is_engaged() {
+                    self.defense.disengage();
+                } else {
+                    self.defense.engage(position, heading);
+                }
+                info!("defense engaged: {}", self.defense.is_engaged());
+            }
+
+            // touching the sticks gives control back to the driver
+            let sticks_idle = [state.left_stick.x(), throttle, turn]
+                .iter()
+                .all(|input| input.abs() < consts::DEADZONE);
+            if !sticks_idle && self.defense.is_engaged() {
+                self.defense.disengage();
+                info!("defense engaged: false");
+            }
+
+            let (linear, angular) = self.curvature.arcade(throttle, turn);
+            let correction = self.heading_hold.update(heading, throttle, turn);
+            let field_centric =
+                self.field_centric
+                    .update(heading, state.left_stick.x(), state.left_stick.y());
+
+            if let Some((linear, angular)) = self.defense.update(position, heading) {
+                braked = None;
+                self.dt
+                    .model
+                    .drive_arcade(linear, angular)
+                    .unwrap_or_else(|e| warn!("couldn't drive drivetrain: {e}"));
+            } else if sticks_idle {
+                // brake once when the sticks are let go (or the brake mode changes while they're
+                // idle), the motors keep braking by themselves
+                if braked != Some(self.brake_mode) {
+                    match self.drive_motors.brake(self.brake_mode) {
+                        Ok(()) => braked = Some(self.brake_mode),
+                        Err(e) => warn!("couldn't brake drivetrain: {e}"),
+                    }
+                }
+            } else {
+                braked = None;
+                let (linear, angular) = field_centric.unwrap_or((linear, angular + correction));
+                self.dt
+                    .model
+                    .drive_arcade(linear, angular)
+                    .unwrap_or_else(|e| warn!("couldn't drive drivetrain: {e}"));
+            }
+
+            // rumble once when the robot starts tipping, so the driver knows why it stopped
+            // responding
+            let tipping = matches!(self.dt.model.tilt(), Tilt::Tipping(_));
+            if tipping && !was_tipping {
+                let _ = self.ctrl.try_rumble("---");
+            }
+            was_tipping = tipping;
 
             // Priority:
             // L2 => Score Long
@@ -143,25 +304,148 @@ async fn main(mut peris: Peripherals) {
     );
     let intake_command = intake.command();
 
-    let left_motors = shared_motors![Motor::new(peris.port_3, Gearset::Green, Direction::Reverse),];
-    let right_motors =
-        shared_motors![Motor::new(peris.port_4, Gearset::Green, Direction::Reverse),];
-    let jodio = Jodio {
-        dt: Drivetrain {
-            model: Differential::new([], []),
-            tracking: WheeledTracking::forward_only(
-                (0.0, 0.0),
-                Angle::from_radians(0.0),
-                [
-                    TrackingWheel::new(left_motors.clone(), 3.25, 0.0, Some(36.0 / 48.0)),
-                    TrackingWheel::new(right_motors.clone(), 3.25, 0.0, Some(36.0 / 48.0)),
-                ],
-                {
-                    let a: Option<InertialSensor> = None;
-                    a
-                },
-            ),
+    // SAFETY: the imu port isn't taken from `peris` anywhere else.
+    let mut imu = InertialSensor::new(unsafe { SmartPort::new(consts::IMU_PORT) });
+    let imu_calibrated = imu::calibrate(&mut imu, &mut peris.display).await;
+    let imu = SharedImu(Rc::new(imu));
+
+    let config = RobotConfig::load(
+        consts::ROBOT_CONFIG_PATH,
+        RobotConfig {
+            wheel_diameter: consts::DRIVE.wheel_diameter,
+            track_width: consts::DRIVE.track_width,
+            tracking_wheel_diameter: consts::TRACKING_WHEEL_DIAMETER,
         },
+    );
+
+    // SAFETY: the drive ports aren't taken from `peris` anywhere else.
+    let drive = unsafe { config.apply_drive(consts::DRIVE).build() };
+    drive.warn_disconnected(&mut peris.display).await;
+    let parallel_wheels = consts::PARALLEL_WHEELS
+        .iter()
+        .map(|wheel| config.apply_tracking(*wheel))
+        .collect::<Vec<_>>();
+    // SAFETY: the tracking wheel ports aren't taken from `peris` anywhere else.
+    let (odometry, odometry_source) = unsafe {
+        tracking_config::build(
+            &parallel_wheels,
+            consts::PERPENDICULAR_WHEEL.map(|wheel| config.apply_tracking(wheel)),
+            drive.tracking_wheels,
+            imu_calibrated.then(|| imu.clone()),
+        )
+    };
+
+    // SAFETY: the gps port isn't taken from `peris` anywhere else.
+    let gps = consts::GPS_PORT
+        .map(|port| unsafe {
+            GpsSensor::new(SmartPort::new(port), consts::GPS_OFFSET, [0.0, 0.0], 0.0)
+        })
+        .filter(|sensor| {
+            let connected = sensor.is_connected();
+            if !connected {
+                warn!("gps isn't connected, tracking with odometry only");
+            }
+            connected
+        })
+        .map(|sensor| GpsSource {
+            sensor,
+            rotation: consts::GPS_ROTATION,
+            max_error: consts::GPS_MAX_ERROR,
+            max_speed: consts::GPS_MAX_SPEED,
+            heading_error: consts::GPS_HEADING_ERROR,
+        });
+
+    let tracking = FusedTracking::new(
+        odometry,
+        gps,
+        imu_calibrated
+            .then(|| HeadingFallback::new(imu.clone(), drive.motors.clone(), config.track_width)),
+        consts::ODOMETRY_LINEAR_NOISE,
+        consts::ODOMETRY_ANGULAR_NOISE,
+        consts::GPS_GATE,
+    );
+
+    // SAFETY: the distance sensor ports aren't taken from `peris` anywhere else.
+    let distance_sensors = unsafe { relocalize::mount_sensors(&consts::DISTANCE_SENSORS) };
+    let localizer = Localizer::new(
+        Mcl::new(
+            Default::default(),
+            consts::MCL_PARTICLES,
+            0.0,
+            consts::ODOMETRY_LINEAR_NOISE,
+            consts::ODOMETRY_ANGULAR_NOISE,
+            consts::MCL_SENSOR_NOISE,
+            consts::MCL_SEED,
+        ),
+        distance_sensors.clone(),
+        tracking.reader(),
+        consts::MCL_START_SPREAD,
+        consts::MCL_MAX_SPREAD,
+    );
+    let trace = TraceRecorder::new(tracking.reader(), consts::TRACE_INTERVAL);
+    let monitor = MotionMonitor::new(
+        drive.motors.clone(),
+        imu.0.clone(),
+        config.track_width,
+        consts::MONITOR_SLIP_THRESHOLD,
+        consts::MONITOR_PUSH_THRESHOLD,
+        consts::MONITOR_PUSH_TURN_THRESHOLD,
+        consts::MONITOR_COLLISION_ACCELERATION,
+        consts::IMU_FORWARD_AXIS,
+    );
+
+    let model = TipGuard::new(
+        TractionControl::new(
+            drive.model,
+            drive.motors.clone(),
+            imu.0.clone(),
+            consts::MAX_ACCELERATION,
+            consts::SLIP_THRESHOLD,
+            consts::SLIP_POWER_SCALE,
+            consts::IMU_FORWARD_AXIS,
+        ),
+        imu.0.clone(),
+        consts::TIP_LEAN_ANGLE,
+        consts::TIP_ANGLE,
+        consts::TIP_LEAN_POWER_SCALE,
+        consts::TIP_RECOVERY_POWER,
+        consts::IMU_FORWARD_AXIS,
+    );
+    let motion = Motion {
+        linear_controller: consts::LINEAR_PID,
+        angular_controller: consts::ANGULAR_PID,
+        linear_tolerances: consts::LINEAR_TOLERANCES,
+        angular_tolerances: consts::ANGULAR_TOLERANCES,
+        timeout: consts::MOTION_TIMEOUT,
+        speed: consts::MOTION_SPEED,
+        turn_speed: consts::MOTION_TURN_SPEED,
+        drive_limits: consts::DRIVE_LIMITS,
+        turn_limits: consts::TURN_LIMITS,
+        linear_feedforward: consts::LINEAR_FEEDFORWARD,
+        angular_feedforward: consts::ANGULAR_FEEDFORWARD,
+        boomerang_lead: consts::BOOMERANG_LEAD,
+        boomerang_settle_radius: consts::BOOMERANG_SETTLE_RADIUS,
+        pure_pursuit: PurePursuit {
+            track_width: config.track_width,
+            ..consts::PURE_PURSUIT
+        },
+        ramsete: Ramsete {
+            track_width: config.track_width,
+            ..consts::RAMSETE
+        },
+        watchdog: Watchdog::new(
+            tracking.reader(),
+            drive.motors.clone(),
+            model.tilt_reader(),
+            consts::STALL_SPEED,
+            consts::STALL_TIME,
+        ),
+    };
+
+    let (sd_routes, mut route_errors) = route::load_sd(consts::SD_ROUTES_PATH);
+
+    let jodio = Jodio {
+        dt: Drivetrain { model, tracking },
         curvature: CurvatureDrive::new(
             consts::TURN_NONLINEARITY,
             consts::DEADZONE,
@@ -169,6 +453,15 @@ async fn main(mut peris: Peripherals) {
             consts::NEGATIVE_INERTIA_SCALAR,
             consts::TURN_SENSITIVITY,
         ),
+        heading_hold: HeadingHold::new(consts::HEADING_HOLD_PID, consts::DEADZONE),
+        field_centric: FieldCentric::new(
+            consts::FIELD_CENTRIC_PID,
+            consts::DEADZONE,
+            consts::DRIVER_FORWARD,
+        ),
+        defense: Defense::new(consts::DEFENSE_LINEAR_PID, consts::DEFENSE_ANGULAR_PID),
+        drive_motors: drive.motors,
+        brake_mode: BrakeMode::Coast,
         _intake_task: spawn(async move {
             loop {
                 let _ = intake.update();
@@ -177,19 +470,56 @@ async fn main(mut peris: Peripherals) {
         }),
         intake_command,
         matchloader: Matchloader::new(peris.adi_b),
+        relocalizer: Relocalizer::new(
+            distance_sensors.clone(),
+            consts::RELOCALIZE_SQUARE_TOLERANCE,
+            consts::RELOCALIZE_MAX_CORRECTION,
+        ),
+        localizer,
+        trace,
+        monitor,
+        motion,
+        config,
+        odometry: odometry_source,
+        calibration_imu: imu_calibrated.then_some(imu.0),
+        sd_routes,
         ctrl: peris.primary_controller,
         allegiance,
     };
 
+    let mut routes = vec![
+        placed_route!("Right Safe", Some(auton::SAFE_START), auton::right_safe),
+        placed_route!(
+            "Left Safe",
+            Some(auton::SAFE_START.mirrored()),
+            auton::left_safe
+        ),
+        placed_route!("Right AWP", Some(auton::AWP_START), auton::awp),
+    ];
+    routes.extend(route::embedded());
+    let sd_slots = placement::MAX_ROUTES.saturating_sub(routes.len());
+    routes.extend(route::sd_slots(
+        &jodio.sd_routes,
+        sd_slots,
+        &mut route_errors,
+    ));
+    let Ok(routes) = <[_; placement::MAX_ROUTES]>::try_from(routes) else {
+        panic!("only {} routes fit in the selector", placement::MAX_ROUTES);
+    };
+
+    // SAFETY: the placement check only draws on the half of the screen `SimpleSelect` leaves empty
+    let _placement = PlacementCheck::new(
+        unsafe { Display::new() },
+        &routes,
+        distance_sensors,
+        consts::START_TOLERANCE,
+        route_errors,
+    );
+
     jodio
         .compete(SimpleSelect::new(
             peris.display,
-            [
-                route!("Right Safe", auton::right_safe),
-                route!("Left Safe", auton::left_safe),
-                route!("Right AWP", auton::awp),
-                route!("Skills", auton::skills),
-            ],
+            routes.map(|placed| placed.route),
         ))
         .await;
 }
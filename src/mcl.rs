@@ -0,0 +1,484 @@
+//! Monte Carlo localization against the field map.
+//!
+//! This module only depends on `std` so that it can be run on a computer against simulated sensor
+//! readings (see `host/`).
+
+use std::f64::consts::PI;
+
+use crate::{
+    ekf::{Pose, wrap},
+    field,
+    rng::Rng,
+};
+
+/// Largest distance (in inches) a distance sensor can report.
+pub const MAX_RANGE: f64 = 78.0;
+
+/// Fraction of readings expected to be unrelated to the field map, such as readings off of other
+/// robots or game elements.
+const OUTLIER_RATIO: f64 = 0.1;
+
+/// Noise added to every particle on every prediction, so that particles that were resampled from
+/// the same parent spread back out even while the robot is standing still.
+const BASE_LINEAR_NOISE: f64 = 0.02;
+const BASE_ANGULAR_NOISE: f64 = 0.002;
+
+/// A distance sensor's position on the robot, with x forwards, y to the left, and `angle` being
+/// the direction it faces relative to the front of the robot in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Beam {
+    pub x: f64,
+    pub y: f64,
+    pub angle: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Particle {
+    pose: Pose,
+    log_weight: f64,
+}
+
+/// Particle Filter Localizer
+///
+/// Tracks a cloud of guesses (particles) of where the robot might be. Every particle is moved by
+/// odometry with some added noise, then weighted by how well the distance sensor readings match
+/// what the sensors would have seen from that particle's pose on the [field map](crate::field).
+/// Unlikely particles are periodically replaced with copies of likely ones, so the cloud collapses
+/// around the robot's true pose.
+///
+/// Unlike wall relocalization, this can use readings off of any part of the map from any angle,
+/// and doesn't need the robot to be square to anything.
+///
+/// # Constants
+///
+/// * `linear_noise` - Standard deviation of odometry's linear error per inch travelled.
+/// * `angular_noise` - Standard deviation of odometry's heading error per radian turned.
+/// * `sensor_noise` - Standard deviation of a distance sensor reading in inches.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mcl {
+    pub linear_noise: f64,
+    pub angular_noise: f64,
+    pub sensor_noise: f64,
+
+    particles: Vec<Particle>,
+    rng: Rng,
+}
+
+impl Mcl {
+    /// Constructs a filter with `count` particles scattered around `pose`.
+    pub fn new(
+        pose: Pose,
+        count: usize,
+        spread: f64,
+        linear_noise: f64,
+        angular_noise: f64,
+        sensor_noise: f64,
+        seed: u64,
+    ) -> Self {
+        let mut mcl = Self {
+            linear_noise,
+            angular_noise,
+            sensor_noise,
+            particles: vec![
+                Particle {
+                    pose,
+                    log_weight: 0.0,
+                };
+                count
+            ],
+            rng: Rng::new(seed),
+        };
+        mcl.reset(pose, spread);
+        mcl
+    }
+
+    /// Scatters every particle around `pose`, with positions `spread` inches apart on average.
+    /// Headings are scattered by a proportional amount.
+    pub fn reset(&mut self, pose: Pose, spread: f64) {
+        let heading_spread = spread / field::WALL * PI / 4.0;
+        let log_weight = -(self.particles.len() as f64).ln();
+        for particle in &mut self.particles {
+            particle.pose = Pose {
+                x: pose.x + self.rng.gaussian(spread),
+                y: pose.y + self.rng.gaussian(spread),
+                heading: wrap(pose.heading + self.rng.gaussian(heading_spread)),
+            };
+            particle.log_weight = log_weight;
+        }
+    }
+
+    /// Moves every particle by a motion measured by odometry.
+    ///
+    /// `forward` and `sideways` are the distances travelled relative to the robot's heading at the
+    /// start of the motion, and `turn` is the change in heading in radians.
+    pub fn predict(&mut self, forward: f64, sideways: f64, turn: f64) {
+        let distance = forward.hypot(sideways);
+        let linear_noise = self.linear_noise * distance + BASE_LINEAR_NOISE;
+        let angular_noise = self.angular_noise * turn.abs() + BASE_ANGULAR_NOISE;
+
+        for particle in &mut self.particles {
+            let forward = forward + self.rng.gaussian(linear_noise);
+            let sideways = sideways + self.rng.gaussian(linear_noise);
+            let turn = turn + self.rng.gaussian(angular_noise);
+
+            let pose = &mut particle.pose;
+            let (sin, cos) = pose.heading.sin_cos();
+            pose.x += forward * cos - sideways * sin;
+            pose.y += forward * sin + sideways * cos;
+            pose.heading = wrap(pose.heading + turn);
+        }
+    }
+
+    /// Weighs every particle by how well it explains a set of distance sensor readings, then
+    /// resamples if too few particles are still likely.
+    ///
+    /// Readings are in inches, and readings past [`MAX_RANGE`] should be left out.
+    pub fn update(&mut self, readings: &[(Beam, f64)]) {
+        if readings.is_empty() {
+            return;
+        }
+
+        let variance = 2.0 * self.sensor_noise.powi(2);
+        let normalization = 1.0 / (PI * variance).sqrt();
+
+        for particle in &mut self.particles {
+            let pose = particle.pose;
+            let (sin, cos) = pose.heading.sin_cos();
+
+            // particles that have wandered off the field can't be right
+            if pose.x.abs() > field::WALL || pose.y.abs() > field::WALL {
+                particle.log_weight = f64::NEG_INFINITY;
+                continue;
+            }
+
+            for (beam, distance) in readings {
+                let origin = [
+                    pose.x + beam.x * cos - beam.y * sin,
+                    pose.y + beam.x * sin + beam.y * cos,
+                ];
+                let expected = field::raycast(origin, pose.heading + beam.angle)
+                    .unwrap_or(MAX_RANGE)
+                    .min(MAX_RANGE);
+
+                // a reading either comes from the map with some noise, or from something else
+                let hit = normalization * (-(distance - expected).powi(2) / variance).exp();
+                let likelihood = (1.0 - OUTLIER_RATIO) * hit + OUTLIER_RATIO / MAX_RANGE;
+                particle.log_weight += likelihood.ln();
+            }
+        }
+
+        self.normalize();
+        if self.effective_count() < self.particles.len() as f64 / 2.0 {
+            self.resample();
+        }
+    }
+
+    /// The weighted average pose of every particle.
+    pub fn estimate(&self) -> Pose {
+        let (mut x, mut y, mut sin, mut cos) = (0.0, 0.0, 0.0, 0.0);
+        for (particle, weight) in self.weighted() {
+            x += particle.pose.x * weight;
+            y += particle.pose.y * weight;
+            sin += particle.pose.heading.sin() * weight;
+            cos += particle.pose.heading.cos() * weight;
+        }
+
+        Pose {
+            x,
+            y,
+            heading: sin.atan2(cos),
+        }
+    }
+
+    /// Weighted root mean square distance between the particles and [`Mcl::estimate`], in inches.
+    ///
+    /// A small spread means the filter is confident in its estimate.
+    pub fn spread(&self) -> f64 {
+        let estimate = self.estimate();
+        self.weighted()
+            .map(|(particle, weight)| {
+                weight
+                    * ((particle.pose.x - estimate.x).powi(2)
+                        + (particle.pose.y - estimate.y).powi(2))
+            })
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    fn weighted(&self) -> impl Iterator<Item = (&Particle, f64)> {
+        self.particles
+            .iter()
+            .map(|particle| (particle, particle.log_weight.exp()))
+    }
+
+    /// Shifts log weights so that they sum to one, keeping them in a range that won't underflow.
+    fn normalize(&mut self) {
+        let max = self
+            .particles
+            .iter()
+            .map(|particle| particle.log_weight)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        if max == f64::NEG_INFINITY {
+            // every particle is impossible, so start over from equal weights and hope for the best
+            let log_weight = -(self.particles.len() as f64).ln();
+            for particle in &mut self.particles {
+                particle.log_weight = log_weight;
+            }
+            return;
+        }
+
+        let sum = self
+            .particles
+            .iter()
+            .map(|particle| (particle.log_weight - max).exp())
+            .sum::<f64>();
+        let shift = max + sum.ln();
+        for particle in &mut self.particles {
+            particle.log_weight -= shift;
+        }
+    }
+
+    fn effective_count(&self) -> f64 {
+        1.0 / self
+            .weighted()
+            .map(|(_, weight)| weight.powi(2))
+            .sum::<f64>()
+    }
+
+    /// Low variance resampling, which keeps particles in proportion to their weights with a
+    /// single random number.
+    fn resample(&mut self) {
+        let count = self.particles.len();
+        let step = 1.0 / count as f64;
+        let mut target = self.rng.uniform() * step;
+
+        let mut resampled = Vec::with_capacity(count);
+        let mut cumulative = 0.0;
+        let log_weight = -(count as f64).ln();
+
+        for (particle, weight) in self.weighted() {
+            cumulative += weight;
+            while target < cumulative && resampled.len() < count {
+                resampled.push(Particle {
+                    pose: particle.pose,
+                    log_weight,
+                });
+                target += step;
+            }
+        }
+
+        // rounding can leave the last few slots empty
+        while resampled.len() < count {
+            resampled.push(Particle {
+                pose: self.particles[count - 1].pose,
+                log_weight,
+            });
+        }
+
+        self.particles = resampled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::ekf::{ANGULAR_NOISE, LINEAR_NOISE};
+
+    const DT: f64 = 0.01;
+    const DURATION: f64 = 30.0;
+    const UPDATE_INTERVAL: usize = 5;
+    const PARTICLES: usize = 250;
+
+    // skip the first few seconds of a run before checking the filter's error, so it has time to
+    // converge when it starts out unsure
+    const SETTLE: f64 = 5.0;
+
+    // odometry error model
+    const WHEEL_SCALE_ERROR: f64 = 0.03;
+    const HEADING_DRIFT: f64 = 0.005;
+    const ODOMETRY_NOISE: f64 = 0.02;
+
+    // distance sensor error model
+    const SENSOR_NOISE: f64 = 0.5;
+    const SENSOR_OUTLIER_CHANCE: f64 = 0.05;
+
+    const BEAMS: [Beam; 3] = [
+        Beam {
+            x: -6.0,
+            y: 0.0,
+            angle: PI,
+        },
+        Beam {
+            x: 0.0,
+            y: 6.0,
+            angle: FRAC_PI_2,
+        },
+        Beam {
+            x: 0.0,
+            y: -6.0,
+            angle: -FRAC_PI_2,
+        },
+    ];
+
+    const START: Pose = Pose {
+        x: -60.0,
+        y: 18.0,
+        heading: 0.0,
+    };
+
+    #[derive(Debug, Default)]
+    struct Run {
+        /// RMS position error of raw odometry.
+        odometry_error: f64,
+        /// RMS and largest position error of the filter once it has settled.
+        filter_error: f64,
+        worst_error: f64,
+        /// Position error and particle spread at the end of the run.
+        final_error: f64,
+        final_spread: f64,
+    }
+
+    /// Drives a simulated robot around the field with drifting odometry and noisy distance
+    /// sensors, starting the filter `offset` inches to the right of the true start with particles
+    /// scattered by `spread`.
+    fn simulate(seed: u64, offset: f64, spread: f64) -> Run {
+        let mut rng = Rng::new(seed);
+
+        let mut truth = START;
+        let mut odometry = START;
+        let guess = Pose {
+            x: START.x + offset,
+            ..START
+        };
+        let mut mcl = Mcl::new(
+            guess,
+            PARTICLES,
+            spread,
+            LINEAR_NOISE,
+            ANGULAR_NOISE,
+            SENSOR_NOISE * 2.0,
+            seed,
+        );
+
+        let mut run = Run::default();
+        let mut steps = 0;
+        let mut settled_steps = 0;
+
+        // motion accumulated since the last filter update
+        let mut pending = (0.0, 0.0);
+
+        let mut time = 0.0;
+        while time < DURATION {
+            // wander around the field, turning back towards the center when near the walls
+            let to_center = wrap((-truth.y).atan2(-truth.x) - truth.heading);
+            let turn_rate = if truth.x.hypot(truth.y) > 45.0 {
+                1.5 * to_center.signum()
+            } else {
+                0.6 * (time * 0.3).sin()
+            };
+
+            let forward = 24.0 * DT;
+            let turn = turn_rate * DT;
+            truth = step(truth, forward, turn);
+
+            let measured_forward =
+                forward * (1.0 + WHEEL_SCALE_ERROR) + rng.gaussian(ODOMETRY_NOISE);
+            let measured_turn = turn + HEADING_DRIFT * DT + rng.gaussian(ODOMETRY_NOISE * DT);
+            odometry = step(odometry, measured_forward, measured_turn);
+
+            // the filter runs slower than odometry, so motion is batched between updates
+            pending.0 += measured_forward;
+            pending.1 += measured_turn;
+
+            if steps % UPDATE_INTERVAL == 0 {
+                let readings = BEAMS
+                    .iter()
+                    .filter_map(|beam| Some((*beam, read(&mut rng, truth, beam)?)))
+                    .collect::<Vec<_>>();
+                mcl.predict(pending.0, 0.0, pending.1);
+                mcl.update(&readings);
+                pending = (0.0, 0.0);
+            }
+
+            run.odometry_error += distance(odometry, truth).powi(2);
+            if time >= SETTLE {
+                let error = distance(mcl.estimate(), truth);
+                run.filter_error += error.powi(2);
+                run.worst_error = run.worst_error.max(error);
+                settled_steps += 1;
+            }
+            steps += 1;
+            time += DT;
+        }
+
+        run.odometry_error = (run.odometry_error / steps as f64).sqrt();
+        run.filter_error = (run.filter_error / settled_steps as f64).sqrt();
+        run.final_error = distance(mcl.estimate(), truth);
+        run.final_spread = mcl.spread();
+        run
+    }
+
+    /// Simulates a distance sensor reading, returning `None` if nothing is in range.
+    fn read(rng: &mut Rng, pose: Pose, beam: &Beam) -> Option<f64> {
+        let (sin, cos) = pose.heading.sin_cos();
+        let origin = [
+            pose.x + beam.x * cos - beam.y * sin,
+            pose.y + beam.x * sin + beam.y * cos,
+        ];
+
+        // something that isn't on the map, like another robot
+        if rng.uniform() < SENSOR_OUTLIER_CHANCE {
+            return Some(rng.uniform() * MAX_RANGE);
+        }
+
+        let distance =
+            field::raycast(origin, pose.heading + beam.angle)? + rng.gaussian(SENSOR_NOISE);
+        (distance < MAX_RANGE).then_some(distance)
+    }
+
+    fn step(pose: Pose, forward: f64, turn: f64) -> Pose {
+        Pose {
+            x: pose.x + forward * pose.heading.cos(),
+            y: pose.y + forward * pose.heading.sin(),
+            heading: wrap(pose.heading + turn),
+        }
+    }
+
+    fn distance(a: Pose, b: Pose) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    #[test]
+    fn tracks_from_known_start() {
+        for seed in 1..=3 {
+            let run = simulate(seed, 0.0, 1.0);
+            assert!(run.odometry_error > 3.0, "seed {seed}: {run:?}");
+            assert!(run.filter_error < 1.0, "seed {seed}: {run:?}");
+            assert!(run.worst_error < 3.0, "seed {seed}: {run:?}");
+            assert!(run.final_error < 1.5, "seed {seed}: {run:?}");
+            assert!(run.final_spread < 1.5, "seed {seed}: {run:?}");
+        }
+    }
+
+    #[test]
+    fn converges_from_rough_start() {
+        for seed in 1..=3 {
+            let run = simulate(seed, 6.0, 6.0);
+            assert!(run.filter_error < 1.0, "seed {seed}: {run:?}");
+            assert!(run.worst_error < 3.0, "seed {seed}: {run:?}");
+            assert!(run.final_spread < 1.5, "seed {seed}: {run:?}");
+        }
+    }
+
+    #[test]
+    fn empty_readings_leave_weights_alone() {
+        let mut mcl = Mcl::new(START, PARTICLES, 4.0, LINEAR_NOISE, ANGULAR_NOISE, 1.0, 1);
+        let before = mcl.clone();
+
+        mcl.update(&[]);
+        assert_eq!(mcl, before);
+    }
+}
@@ -0,0 +1,351 @@
+use std::{fmt, time::Duration};
+
+use evian::{
+    control::loops::{AngularPid, Pid},
+    drivetrain::model::Arcade,
+    math::{Angle, Vec2},
+    prelude::*,
+};
+use log::info;
+
+use crate::{
+    boomerang::Boomerang,
+    ekf::wrap,
+    path::PathPoint,
+    profile::{Limits, MotionProfile},
+    profiled::{Feedforward, ProfiledMotion},
+    pure_pursuit::PurePursuit,
+    ramsete::Ramsete,
+    trajectory::Trajectory,
+    watchdog::{MotionResult, Outcome, Target, Watchdog},
+};
+
+/// The result of following an empty path or trajectory.
+const NOTHING_TO_DO: MotionResult = MotionResult {
+    outcome: Outcome::Settled,
+    error: 0.0,
+    duration: Duration::ZERO,
+};
+
+/// Motion Context
+///
+/// The controllers, tolerances, timeout and speed limits that autonomous motions run with, shared
+/// by every route so they don't each have to build their own. Every motion is logged with the
+/// settings it runs with, and is run under the [`Watchdog`] so it can't hang the route.
+///
+/// Settings can be overridden for part of a route by making a modified copy, which leaves the
+/// defaults alone for everything after it:
+///
+/// ```
+/// let careful = jodio
+///     .motion
+///     .with_speed(0.5)
+///     .with_linear_tolerances(consts::LINEAR_TOLERANCES.error(2.0));
+/// careful.drive_distance(&mut jodio.dt, 12.0).await;
+/// ```
+#[derive(Clone)]
+pub struct Motion {
+    pub linear_controller: Pid,
+    pub angular_controller: AngularPid,
+    pub linear_tolerances: Tolerances,
+    pub angular_tolerances: Tolerances,
+
+    /// How long a motion can run before it's stopped.
+    pub timeout: Duration,
+
+    /// Fraction of full speed to drive at, between 0 and 1. Scales the drive profile's max
+    /// velocity, path points' speeds, and the boomerang's largest power.
+    pub speed: f64,
+
+    /// Fraction of full speed to turn in place at, between 0 and 1. Scales the turn profile's max
+    /// velocity.
+    pub turn_speed: f64,
+
+    /// Profile limits for straight drives at full speed, in inches.
+    pub drive_limits: Limits,
+
+    /// Profile limits for turns in place at full speed, in radians.
+    pub turn_limits: Limits,
+
+    /// See [`ProfiledMotion::linear_feedforward`].
+    pub linear_feedforward: Feedforward,
+
+    /// See [`ProfiledMotion::angular_feedforward`].
+    pub angular_feedforward: Feedforward,
+
+    /// See [`Boomerang::lead`].
+    pub boomerang_lead: f64,
+
+    /// See [`Boomerang::settle_radius`].
+    pub boomerang_settle_radius: f64,
+
+    /// Used by [`Motion::follow_path`].
+    pub pure_pursuit: PurePursuit,
+
+    /// Used by [`Motion::follow_trajectory`].
+    pub ramsete: Ramsete,
+
+    pub watchdog: Watchdog,
+}
+
+impl Motion {
+    /// Returns a copy that drives with `speed` as its largest linear power.
+    pub fn with_speed(&self, speed: f64) -> Self {
+        Self {
+            speed,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy that turns with `turn_speed` as its largest angular power.
+    pub fn with_turn_speed(&self, turn_speed: f64) -> Self {
+        Self {
+            turn_speed,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy that settles drives with `linear_tolerances`.
+    pub fn with_linear_tolerances(&self, linear_tolerances: Tolerances) -> Self {
+        Self {
+            linear_tolerances,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy that settles turns with `angular_tolerances`.
+    pub fn with_angular_tolerances(&self, angular_tolerances: Tolerances) -> Self {
+        Self {
+            angular_tolerances,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy that gives motions `timeout` to finish.
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            ..self.clone()
+        }
+    }
+
+    /// Drives `distance` inches straight ahead, or backwards if it's negative, following a
+    /// [`MotionProfile`].
+    pub async fn drive_distance<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        distance: f64,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksForwardTravel + TracksHeading + TracksVelocity,
+    {
+        let profile = MotionProfile::new(distance, self.drive_limits.scaled(self.speed));
+        self.log(format_args!(
+            "drive_distance({distance:.2}) over {:.2} s",
+            profile.duration()
+        ));
+        self.watchdog
+            .watch(
+                Target::Distance(distance),
+                self.profile_timeout(&profile),
+                self.profiled(&profile).drive(drivetrain, &profile),
+            )
+            .await
+    }
+
+    /// Turns in place to `heading` the short way around, following a [`MotionProfile`].
+    pub async fn turn_to_heading<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        heading: Angle,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksHeading + TracksVelocity,
+    {
+        self.log(format_args!(
+            "turn_to_heading({:.1} deg)",
+            heading.as_degrees()
+        ));
+        self.turn(drivetrain, heading, Target::Heading(heading))
+            .await
+    }
+
+    /// Turns in place to face `point` the short way around, following a [`MotionProfile`].
+    pub async fn turn_to_point<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        point: Vec2<f64>,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksPosition + TracksHeading + TracksVelocity,
+    {
+        self.log(format_args!(
+            "turn_to_point({:.1}, {:.1})",
+            point.x, point.y
+        ));
+        let position = drivetrain.tracking.position();
+        let heading = Angle::from_radians((point.y - position.y).atan2(point.x - position.x));
+        self.turn(drivetrain, heading, Target::Facing(point)).await
+    }
+
+    /// Drives to `point` and ends facing `heading` with a [`Boomerang`], backing in if `reverse`
+    /// is set.
+    pub async fn move_to_pose<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        point: Vec2<f64>,
+        heading: Angle,
+        reverse: bool,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksPosition + TracksHeading + TracksVelocity,
+    {
+        self.log(format_args!(
+            "move_to_pose({:.1}, {:.1}, {:.1} deg, reverse: {reverse})",
+            point.x,
+            point.y,
+            heading.as_degrees()
+        ));
+        let boomerang = Boomerang {
+            linear_controller: self.linear_controller,
+            angular_controller: self.angular_controller,
+            linear_tolerances: self.linear_tolerances,
+            angular_tolerances: self.angular_tolerances,
+            lead: self.boomerang_lead,
+            settle_radius: self.boomerang_settle_radius,
+            max_speed: self.speed,
+            timeout: Some(self.timeout),
+        };
+        self.watchdog
+            .watch(
+                Target::Point(point),
+                self.timeout,
+                boomerang.move_to_pose(drivetrain, point, heading, reverse),
+            )
+            .await
+    }
+
+    /// Follows `path` with [`PurePursuit`], facing backwards if `reverse` is set.
+    pub async fn follow_path<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        path: &[PathPoint],
+        reverse: bool,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksPosition + TracksHeading + TracksVelocity,
+    {
+        let Some(end) = path.last() else {
+            return NOTHING_TO_DO;
+        };
+        self.log(format_args!(
+            "follow_path({} points to ({:.1}, {:.1}), reverse: {reverse})",
+            path.len(),
+            end.x,
+            end.y
+        ));
+
+        let path = path
+            .iter()
+            .map(|point| PathPoint {
+                speed: point.speed * self.speed,
+                ..*point
+            })
+            .collect::<Vec<_>>();
+        let pursuit = PurePursuit {
+            timeout: Some(self.timeout),
+            ..self.pure_pursuit
+        };
+        self.watchdog
+            .watch(
+                Target::Point(Vec2::new(end.x, end.y)),
+                self.timeout,
+                pursuit.follow(drivetrain, &path, reverse),
+            )
+            .await
+    }
+
+    /// Drives `trajectory` with [`Ramsete`].
+    ///
+    /// The trajectory already says how fast to drive and how long it takes, so the speed limit
+    /// doesn't apply and the timeout is on top of the trajectory's duration.
+    pub async fn follow_trajectory<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        trajectory: &Trajectory,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksPosition + TracksHeading,
+    {
+        let Some(end) = trajectory.states.last() else {
+            return NOTHING_TO_DO;
+        };
+        self.log(format_args!(
+            "follow_trajectory({:.2} s to ({:.1}, {:.1}))",
+            trajectory.duration(),
+            end.x,
+            end.y
+        ));
+        self.watchdog
+            .watch(
+                Target::Point(Vec2::new(end.x, end.y)),
+                Duration::from_secs_f64(trajectory.duration()) + self.timeout,
+                self.ramsete.follow(drivetrain, trajectory),
+            )
+            .await
+    }
+
+    async fn turn<M, T>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        heading: Angle,
+        target: Target,
+    ) -> MotionResult
+    where
+        M: Arcade,
+        T: TracksHeading + TracksVelocity,
+    {
+        let turn = wrap(heading.as_radians() - drivetrain.tracking.heading().as_radians());
+        let profile = MotionProfile::new(turn, self.turn_limits.scaled(self.turn_speed));
+        self.watchdog
+            .watch(
+                target,
+                self.profile_timeout(&profile),
+                self.profiled(&profile).turn(drivetrain, &profile),
+            )
+            .await
+    }
+
+    /// Profiled motions get their timeout on top of however long the profile takes, so that slow
+    /// speeds and long drives don't time out before they're done.
+    fn profile_timeout(&self, profile: &MotionProfile) -> Duration {
+        Duration::from_secs_f64(profile.duration()) + self.timeout
+    }
+
+    fn profiled(&self, profile: &MotionProfile) -> ProfiledMotion {
+        ProfiledMotion {
+            linear_controller: self.linear_controller,
+            angular_controller: self.angular_controller,
+            linear_tolerances: self.linear_tolerances,
+            angular_tolerances: self.angular_tolerances,
+            linear_feedforward: self.linear_feedforward,
+            angular_feedforward: self.angular_feedforward,
+            timeout: Some(self.profile_timeout(profile)),
+        }
+    }
+
+    fn log(&self, motion: fmt::Arguments<'_>) {
+        info!(
+            "{motion} at speed {:.2}, turn speed {:.2}, timeout {:.1} s",
+            self.speed,
+            self.turn_speed,
+            self.timeout.as_secs_f64()
+        );
+    }
+}
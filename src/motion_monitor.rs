@@ -0,0 +1,205 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use vexide::{prelude::*, task::Task};
+
+use crate::{
+    drive_motors::DriveMotors,
+    imu::{ImuAxis, ImuVelocity},
+};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Something that moved the robot differently from how its wheels turned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Disturbance {
+    /// The wheels are turning faster than the robot is moving.
+    Slip,
+
+    /// The robot is moving or turning faster than its wheels are, so something is pushing it.
+    Pushed,
+
+    /// The robot hit something (or something hit it) hard enough to jolt the IMU.
+    Collision,
+}
+
+impl Disturbance {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Slip => "wheel slip",
+            Self::Pushed => "push",
+            Self::Collision => "collision",
+        }
+    }
+}
+
+/// Slip and Collision Monitor
+///
+/// Compares the motion measured by the drive encoders with the IMU's acceleration and rotation
+/// rate in the background, and logs whenever they disagree enough that the robot must have
+/// slipped, been pushed, or collided with something.
+///
+/// Odometry can't see any of these, so autonomous routes should check
+/// [`MotionMonitor::take_disturbance`] between motions and stop trusting the tracked pose when it
+/// returns something. During driver control the log is all there is, for reviewing after a match.
+pub struct MotionMonitor {
+    state: Rc<RefCell<MonitorState>>,
+    _task: Task<()>,
+}
+
+struct Thresholds {
+    track_width: f64,
+    slip_threshold: f64,
+    push_threshold: f64,
+    push_turn_threshold: f64,
+    collision_acceleration: f64,
+}
+
+struct MonitorState {
+    imu_velocity: ImuVelocity,
+
+    // the disturbance that's happening right now and when it started, so each one is only logged
+    // once
+    current: Option<(Disturbance, Instant)>,
+
+    // the worst disturbance since the last time a route checked
+    worst: Option<Disturbance>,
+}
+
+impl MotionMonitor {
+    /// Starts monitoring the drivetrain.
+    ///
+    /// # Constants
+    ///
+    /// * `track_width` - Distance between the left and right drive wheels.
+    /// * `slip_threshold` - How far wheel velocity (in inches per second) can run ahead of IMU
+    ///   velocity before the wheels are considered to be slipping.
+    /// * `push_threshold` - How far IMU velocity (in inches per second) can run ahead of wheel
+    ///   velocity before the robot is considered to be pushed.
+    /// * `push_turn_threshold` - How far the IMU's rotation rate (in radians per second) can differ
+    ///   from the wheels' before the robot is considered to be pushed.
+    /// * `collision_acceleration` - Horizontal acceleration (in g) that counts as a collision.
+    /// * `imu_axis` - Which of the IMU's axes points towards the front of the robot.
+    pub fn new(
+        motors: DriveMotors,
+        imu: Rc<InertialSensor>,
+        track_width: f64,
+        slip_threshold: f64,
+        push_threshold: f64,
+        push_turn_threshold: f64,
+        collision_acceleration: f64,
+        imu_axis: ImuAxis,
+    ) -> Self {
+        let state = Rc::new(RefCell::new(MonitorState {
+            imu_velocity: ImuVelocity::new(imu_axis),
+            current: None,
+            worst: None,
+        }));
+        let thresholds = Thresholds {
+            track_width,
+            slip_threshold,
+            push_threshold,
+            push_turn_threshold,
+            collision_acceleration,
+        };
+
+        Self {
+            _task: spawn(Self::task(state.clone(), motors, imu, thresholds)),
+            state,
+        }
+    }
+
+    async fn task(
+        state: Rc<RefCell<MonitorState>>,
+        motors: DriveMotors,
+        imu: Rc<InertialSensor>,
+        thresholds: Thresholds,
+    ) {
+        let mut prev_time = Instant::now();
+        loop {
+            let now = Instant::now();
+            let dt = (now - prev_time).as_secs_f64();
+            prev_time = now;
+
+            {
+                let mut state = state.borrow_mut();
+                let disturbance = state.detect(&motors, &imu, &thresholds, dt);
+                state.record(disturbance, now);
+            }
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+    }
+
+    /// Returns the worst disturbance since this was last called, if there was one.
+    pub fn take_disturbance(&mut self) -> Option<Disturbance> {
+        self.state.borrow_mut().worst.take()
+    }
+}
+
+impl MonitorState {
+    fn detect(
+        &mut self,
+        motors: &DriveMotors,
+        imu: &InertialSensor,
+        thresholds: &Thresholds,
+        dt: f64,
+    ) -> Option<Disturbance> {
+        let (Some(left), Some(right)) = motors.side_velocities() else {
+            return None;
+        };
+        let (Ok(acceleration), Ok(rate)) = (imu.acceleration(), imu.gyro_rate()) else {
+            return None;
+        };
+
+        let wheel_velocity = (left + right) / 2.0;
+        let wheel_turn_rate = (right - left) / thresholds.track_width;
+
+        self.imu_velocity.integrate(imu, dt);
+        let imu_velocity = self.imu_velocity.velocity();
+        // IMU frame is NED, so positive z rotation is clockwise
+        let imu_turn_rate = -rate.z.to_radians();
+
+        let collided = acceleration.x.hypot(acceleration.y) > thresholds.collision_acceleration;
+        let pushed = imu_velocity.abs() - wheel_velocity.abs() > thresholds.push_threshold
+            || (imu_turn_rate - wheel_turn_rate).abs() > thresholds.push_turn_threshold;
+        let slipping = wheel_velocity.abs() - imu_velocity.abs() > thresholds.slip_threshold;
+
+        let disturbance = if collided {
+            Some(Disturbance::Collision)
+        } else if pushed {
+            Some(Disturbance::Pushed)
+        } else if slipping {
+            Some(Disturbance::Slip)
+        } else {
+            None
+        };
+
+        if disturbance.is_none() {
+            self.imu_velocity.blend(wheel_velocity);
+        }
+
+        disturbance
+    }
+
+    fn record(&mut self, disturbance: Option<Disturbance>, now: Instant) {
+        let previous = self.current.map(|(disturbance, _)| disturbance);
+        if disturbance == previous {
+            return;
+        }
+
+        if let Some((previous, start)) = self.current {
+            info!("{} ended after {:?}", previous.describe(), now - start);
+        }
+        if let Some(disturbance) = disturbance {
+            warn!("{} detected", disturbance.describe());
+            self.worst = self.worst.max(Some(disturbance));
+        }
+
+        self.current = disturbance.map(|disturbance| (disturbance, now));
+    }
+}
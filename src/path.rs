@@ -0,0 +1,104 @@
+//! Sampled paths for the path followers.
+//!
+//! A path is a chain of segments, each a straight line or a bezier curve through its control
+//! points, with its own speed limit. Followers don't work with the curves directly. The path is
+//! sampled into evenly spaced points first, so finding the closest point or the lookahead point is
+//! a walk along a list.
+//!
+//! Positions are in inches in the same frame as odometry.
+//!
+//! This module only depends on `std` so that it can be shared with `host/`.
+
+/// How many straight pieces each segment is split into to measure its length.
+const LENGTH_STEPS: usize = 64;
+
+/// Distance in inches between the points routes are sampled into, on the robot and in
+/// `trajectory-gen`.
+pub const SPACING: f64 = 1.0;
+
+/// A piece of a path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathSegment {
+    /// The segment's bezier control points, starting and ending with its end points. Two points
+    /// make a straight line.
+    pub controls: Vec<[f64; 2]>,
+
+    /// The fastest this segment should be driven, as a fraction of full power.
+    pub speed: f64,
+}
+
+/// One sampled point along a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    pub x: f64,
+    pub y: f64,
+
+    /// The speed limit of the segment this point is on.
+    pub speed: f64,
+
+    /// Distance along the path from its start.
+    pub distance: f64,
+}
+
+impl PathSegment {
+    /// A straight segment between two points.
+    pub fn line(start: [f64; 2], end: [f64; 2], speed: f64) -> Self {
+        Self {
+            controls: vec![start, end],
+            speed,
+        }
+    }
+
+    /// The point `t` of the way along the segment, from 0 to 1.
+    pub fn point(&self, t: f64) -> [f64; 2] {
+        // de Casteljau's algorithm
+        let mut points = self.controls.clone();
+        for level in 1..points.len() {
+            for i in 0..points.len() - level {
+                points[i] = [
+                    points[i][0] + (points[i + 1][0] - points[i][0]) * t,
+                    points[i][1] + (points[i + 1][1] - points[i][1]) * t,
+                ];
+            }
+        }
+        points.first().copied().unwrap_or_default()
+    }
+
+    /// The length of the segment, approximated with straight pieces.
+    pub fn length(&self) -> f64 {
+        (1..=LENGTH_STEPS)
+            .map(|i| {
+                let a = self.point((i - 1) as f64 / LENGTH_STEPS as f64);
+                let b = self.point(i as f64 / LENGTH_STEPS as f64);
+                (b[0] - a[0]).hypot(b[1] - a[1])
+            })
+            .sum()
+    }
+}
+
+/// Samples a path into points about `spacing` inches apart, keeping the end point of every
+/// segment.
+pub fn sample(segments: &[PathSegment], spacing: f64) -> Vec<PathPoint> {
+    let mut points: Vec<PathPoint> = Vec::new();
+
+    for segment in segments {
+        let steps = (segment.length() / spacing).ceil().max(1.0) as usize;
+        // the first point of every segment after the first is the last point of the one before it
+        let first = if points.is_empty() { 0 } else { 1 };
+
+        for i in first..=steps {
+            let [x, y] = segment.point(i as f64 / steps as f64);
+            let distance = points
+                .last()
+                .map_or(0.0, |last| last.distance + (x - last.x).hypot(y - last.y));
+            points.push(PathPoint {
+                x,
+                y,
+                speed: segment.speed,
+                distance,
+            });
+        }
+    }
+
+    points
+}
@@ -0,0 +1,393 @@
+use autons::simple::Route;
+use evian::math::{Angle, Vec2};
+use vexide::{
+    display::{Circle, Font, FontFamily, FontSize, Line, Rect, Text, TouchEvent, TouchState},
+    prelude::*,
+    task::Task,
+};
+
+use crate::{
+    field::{self, WALL},
+    mcl::MAX_RANGE,
+    relocalize::{DistanceSensors, MountedSensor},
+};
+
+/// Width of one column of `SimpleSelect`'s route grid.
+const COLUMN_WIDTH: i16 = Display::HORIZONTAL_RESOLUTION / 2;
+/// Height of one row of `SimpleSelect`'s route grid.
+const ROW_HEIGHT: i16 = 40;
+/// How many routes `SimpleSelect` lists in each column.
+const ROWS: i16 = 6;
+
+/// How many routes fit in the left column of `SimpleSelect`, which leaves the right column free
+/// for the placement check.
+pub const MAX_ROUTES: usize = ROWS as usize;
+
+const PANEL_LEFT: i16 = COLUMN_WIDTH;
+
+const MAP_LEFT: i16 = PANEL_LEFT + 10;
+const MAP_TOP: i16 = 10;
+const MAP_SIZE: i16 = 130;
+const ROBOT_RADIUS: i16 = 5;
+
+const TEXT_LEFT: i16 = PANEL_LEFT + 10;
+const TEXT_TOP: i16 = MAP_TOP + MAP_SIZE + 8;
+const LINE_HEIGHT: i16 = 18;
+/// How many characters fit on one line of the panel.
+const LINE_LENGTH: usize = 26;
+
+const PLACED: (u8, u8, u8) = (0, 130, 0);
+const MISPLACED: (u8, u8, u8) = (170, 0, 0);
+const UNVERIFIED: (u8, u8, u8) = (70, 70, 70);
+
+/// Where a route expects the robot to be placed before it starts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartPose {
+    pub position: Vec2<f64>,
+    pub heading: Angle,
+}
+
+impl StartPose {
+    pub const fn new(x: f64, y: f64, heading: Angle) -> Self {
+        Self {
+            position: Vec2::new(x, y),
+            heading,
+        }
+    }
+
+    /// This pose on the other side of the alliance's half of the field, mirrored across the
+    /// x-axis, for running a route on the left instead of the right.
+    pub fn mirrored(self) -> Self {
+        Self {
+            position: Vec2::new(self.position.x, -self.position.y),
+            heading: Angle::from_radians(-self.heading.as_radians()),
+        }
+    }
+}
+
+/// A `SimpleSelect` route along with where it expects to start.
+pub struct PlacedRoute<R> {
+    pub route: Route<R>,
+
+    /// Where the robot should be placed, or `None` if the route doesn't care (like a route file
+    /// without path data).
+    pub start: Option<StartPose>,
+}
+
+/// Creates a [`PlacedRoute`] from a name, an optional [`StartPose`], and an async function that
+/// takes the robot.
+macro_rules! placed_route {
+    ($name:expr, $start:expr, $callback:path) => {
+        $crate::placement::PlacedRoute {
+            route: ::autons::route!($name, $callback),
+            start: $start,
+        }
+    };
+}
+
+pub(crate) use placed_route;
+
+/// Follows which route `SimpleSelect` has selected, since it doesn't say. Like the selector, a
+/// route is selected when a touch is released on the same row it was last held on.
+#[derive(Debug, Default)]
+struct Selection {
+    selected: usize,
+    held: Option<usize>,
+}
+
+impl Selection {
+    fn update(&mut self, touch: TouchEvent, routes: usize) {
+        let index = (ROWS * (touch.point.x / COLUMN_WIDTH) + touch.point.y / ROW_HEIGHT) as usize;
+
+        if matches!(touch.state, TouchState::Pressed | TouchState::Held) {
+            self.held = (index < routes).then_some(index);
+        } else if self.held.take() == Some(index) {
+            self.selected = index;
+        }
+    }
+}
+
+/// Placement Check
+///
+/// Shares the screen with `SimpleSelect`, which lists the routes down the left column. The right
+/// column shows where the selected route expects the robot to be placed, and compares each
+/// distance sensor's reading with what it would read from that pose. The panel turns green once
+/// every sensor agrees to within a tolerance, and red while any of them doesn't, so a misplaced
+/// robot is obvious before the match starts instead of after the route has gone wrong.
+///
+/// Sensors that wouldn't see anything from the start pose can't be checked, and if none of them
+/// can, the panel stays grey.
+///
+/// Route files that couldn't be loaded are listed in place of the placement check until the panel
+/// is touched.
+pub struct PlacementCheck {
+    _task: Task<()>,
+}
+
+impl PlacementCheck {
+    /// Starts drawing the placement check for `routes`, which have to be in the same order as
+    /// they're given to `SimpleSelect`.
+    ///
+    /// `display` only needs to be a second handle to the screen `SimpleSelect` is drawing on.
+    /// `tolerance` is how far (in inches) each distance sensor can be from its expected reading,
+    /// and `errors` are the reasons any route files couldn't be loaded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than [`MAX_ROUTES`] routes, since they'd be listed under the
+    /// panel.
+    pub fn new<R>(
+        display: Display,
+        routes: &[PlacedRoute<R>],
+        sensors: DistanceSensors,
+        tolerance: f64,
+        errors: Vec<String>,
+    ) -> Self {
+        assert!(
+            routes.len() <= MAX_ROUTES,
+            "only {MAX_ROUTES} routes fit next to the placement check"
+        );
+
+        Self {
+            _task: spawn(Self::task(
+                display,
+                routes.iter().map(|route| route.start).collect(),
+                sensors,
+                tolerance,
+                errors,
+            )),
+        }
+    }
+
+    async fn task(
+        mut display: Display,
+        starts: Vec<Option<StartPose>>,
+        sensors: DistanceSensors,
+        tolerance: f64,
+        errors: Vec<String>,
+    ) {
+        let mut selection = Selection::default();
+        let mut drawn = None;
+        let mut showing_errors = !errors.is_empty();
+
+        // `SimpleSelect` clears the whole screen the first time it draws
+        sleep(Display::REFRESH_INTERVAL).await;
+        if showing_errors {
+            draw_errors(&mut display, &errors);
+        }
+
+        loop {
+            let touch = display.touch_status();
+            selection.update(touch, starts.len());
+            if showing_errors && touch.state == TouchState::Pressed && touch.point.x >= PANEL_LEFT {
+                showing_errors = false;
+            }
+
+            if !showing_errors {
+                let start = starts[selection.selected];
+                let check = start.map(|start| check(start, &sensors, tolerance));
+                if drawn.as_ref() != Some(&(start, check.clone())) {
+                    draw_check(&mut display, start, check.as_ref());
+                    drawn = Some((start, check));
+                }
+            }
+
+            sleep(Display::REFRESH_INTERVAL).await;
+        }
+    }
+}
+
+/// One distance sensor's reading compared with what it should read from the start pose, both
+/// rounded to a tenth of an inch so that sensor noise doesn't redraw the screen constantly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SensorCheck {
+    port: u8,
+    expected: f64,
+    measured: Option<f64>,
+    placed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Check(Vec<SensorCheck>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Placed,
+    Misplaced,
+    Unverified,
+}
+
+impl Check {
+    fn status(&self) -> Status {
+        if self.0.is_empty() {
+            Status::Unverified
+        } else if self.0.iter().all(|sensor| sensor.placed) {
+            Status::Placed
+        } else {
+            Status::Misplaced
+        }
+    }
+}
+
+fn check(start: StartPose, sensors: &[MountedSensor], tolerance: f64) -> Check {
+    let (sin, cos) = start.heading.as_radians().sin_cos();
+
+    Check(
+        sensors
+            .iter()
+            .filter_map(|sensor| {
+                let mount = sensor.mount;
+                let origin = [
+                    start.position.x + mount.offset.x * cos - mount.offset.y * sin,
+                    start.position.y + mount.offset.x * sin + mount.offset.y * cos,
+                ];
+                let expected = field::raycast(origin, (start.heading + mount.angle).as_radians())
+                    .filter(|distance| *distance < MAX_RANGE)?;
+                let measured = sensor.distance();
+
+                Some(SensorCheck {
+                    port: mount.port,
+                    expected: round(expected),
+                    measured: measured.map(round),
+                    placed: measured
+                        .is_some_and(|measured| (measured - expected).abs() <= tolerance),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn round(distance: f64) -> f64 {
+    (distance * 10.0).round() / 10.0
+}
+
+fn draw_check(display: &mut Display, start: Option<StartPose>, check: Option<&Check>) {
+    let background = match check.map(Check::status) {
+        Some(Status::Placed) => PLACED,
+        Some(Status::Misplaced) => MISPLACED,
+        Some(Status::Unverified) | None => UNVERIFIED,
+    };
+    fill_panel(display, background);
+
+    let (Some(start), Some(check)) = (start, check) else {
+        draw_text(display, "no start pose", [TEXT_LEFT, TEXT_TOP], background);
+        return;
+    };
+
+    draw_map(display, start);
+
+    let mut lines = vec![format!(
+        "({:.1}, {:.1}) at {:.0} deg",
+        start.position.x,
+        start.position.y,
+        start.heading.as_degrees()
+    )];
+    lines.extend(check.0.iter().map(|sensor| match sensor.measured {
+        Some(measured) => format!(
+            "port {}: {measured:.1}/{:.1} in",
+            sensor.port, sensor.expected
+        ),
+        None => format!("port {}: --/{:.1} in", sensor.port, sensor.expected),
+    }));
+    lines.push(
+        match check.status() {
+            Status::Placed => "placed",
+            Status::Misplaced => "move the robot",
+            Status::Unverified => "no sensor can check this",
+        }
+        .to_string(),
+    );
+
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(
+            display,
+            line,
+            [TEXT_LEFT, TEXT_TOP + i as i16 * LINE_HEIGHT],
+            background,
+        );
+    }
+}
+
+fn draw_errors(display: &mut Display, errors: &[String]) {
+    fill_panel(display, MISPLACED);
+
+    let mut lines = vec![format!("{} routes didn't load:", errors.len())];
+    for error in errors {
+        let chars = error.chars().collect::<Vec<_>>();
+        lines.extend(
+            chars
+                .chunks(LINE_LENGTH)
+                .map(|chunk| chunk.iter().collect::<String>()),
+        );
+    }
+
+    // leave room for the last line
+    let max_lines = (Display::VERTICAL_RESOLUTION - MAP_TOP) / LINE_HEIGHT - 1;
+    lines.truncate(max_lines as usize);
+    lines.push("touch here to dismiss".to_string());
+
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(
+            display,
+            line,
+            [TEXT_LEFT, MAP_TOP + i as i16 * LINE_HEIGHT],
+            MISPLACED,
+        );
+    }
+}
+
+fn fill_panel(display: &mut Display, color: (u8, u8, u8)) {
+    display.fill(
+        &Rect::new(
+            [PANEL_LEFT, 0],
+            [Display::HORIZONTAL_RESOLUTION, Display::VERTICAL_RESOLUTION],
+        ),
+        color,
+    );
+}
+
+/// Draws the field with the robot at its start pose.
+fn draw_map(display: &mut Display, start: StartPose) {
+    let to_screen = |x: f64, y: f64| {
+        let scale = f64::from(MAP_SIZE) / (2.0 * WALL);
+        [
+            MAP_LEFT + ((x + WALL) * scale) as i16,
+            MAP_TOP + ((WALL - y) * scale) as i16,
+        ]
+    };
+
+    display.fill(
+        &Rect::new(
+            [MAP_LEFT, MAP_TOP],
+            [MAP_LEFT + MAP_SIZE, MAP_TOP + MAP_SIZE],
+        ),
+        (90, 90, 90),
+    );
+    for segment in field::SEGMENTS {
+        display.fill(
+            &Line::new(
+                to_screen(segment.start[0], segment.start[1]),
+                to_screen(segment.end[0], segment.end[1]),
+            ),
+            (255, 255, 255),
+        );
+    }
+
+    let (sin, cos) = start.heading.as_radians().sin_cos();
+    let center = to_screen(start.position.x, start.position.y);
+    let front = to_screen(start.position.x + 8.0 * cos, start.position.y + 8.0 * sin);
+    display.fill(&Circle::new(center, ROBOT_RADIUS as u16), (255, 200, 0));
+    display.fill(&Line::new(center, front), (255, 200, 0));
+}
+
+fn draw_text(display: &mut Display, text: &str, position: [i16; 2], background: (u8, u8, u8)) {
+    display.draw_text(
+        &Text::new(
+            text,
+            Font::new(FontSize::SMALL, FontFamily::Monospace),
+            position,
+        ),
+        (255, 255, 255),
+        Some(background.into()),
+    );
+}
@@ -0,0 +1,50 @@
+//! Compile-time bookkeeping of the smart ports the robot uses.
+//!
+//! Most devices are built from port numbers in `consts.rs` with `unsafe` [`SmartPort::new`]
+//! instead of being taken from `Peripherals`, so nothing stops two of them from sharing a port.
+//! Every configured port is added to one [`Ports`] in a `const`, which fails to compile if a port
+//! is out of range or used twice.
+//!
+//! [`SmartPort::new`]: vexide::smart::SmartPort::new
+
+/// Highest smart port number on the brain.
+pub const MAX_PORT: u8 = 21;
+
+/// A set of smart ports that are in use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ports {
+    used: [bool; MAX_PORT as usize + 1],
+}
+
+impl Ports {
+    pub const fn new() -> Self {
+        Self {
+            used: [false; MAX_PORT as usize + 1],
+        }
+    }
+
+    /// Adds `port` to the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port is out of range or is already in the set.
+    pub const fn with(mut self, port: u8) -> Self {
+        assert!(port >= 1 && port <= MAX_PORT, "smart port out of range");
+        assert!(
+            !self.used[port as usize],
+            "smart port used by more than one device"
+        );
+        self.used[port as usize] = true;
+        self
+    }
+
+    /// Adds every port in `ports` to the set, with the same panics as [`Ports::with`].
+    pub const fn with_all(mut self, ports: &[u8]) -> Self {
+        let mut i = 0;
+        while i < ports.len() {
+            self = self.with(ports[i]);
+            i += 1;
+        }
+        self
+    }
+}
@@ -0,0 +1,365 @@
+//! Motion profiles for straight drives and turns in place.
+//!
+//! A profile says how far along a motion the robot should be, and how fast it should be moving
+//! and accelerating, at every moment. It speeds up to a cruising velocity, holds it, then slows
+//! down to stop exactly at the end, without ever going past [`Limits`]. Following one with
+//! feedforward does most of the work before the feedback controller has to correct anything, so
+//! fast motions end up both quick and repeatable.
+//!
+//! Without a jerk limit acceleration jumps straight to its max, giving a trapezoidal velocity
+//! profile. With one, acceleration ramps up and down too (an S-curve), which is gentler on the
+//! wheels' grip.
+//!
+//! Distances are in whatever units the limits are in, inches for drives and radians for turns,
+//! and times are in seconds.
+//!
+//! This module only depends on `std` so that it can be shared with `host/`.
+
+/// How fast a profile can move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_velocity: f64,
+    pub max_acceleration: f64,
+
+    /// How fast acceleration can change, or `None` for a trapezoidal profile.
+    pub max_jerk: Option<f64>,
+}
+
+impl Limits {
+    /// Returns these limits with the max velocity scaled by `speed`, for driving at a fraction of
+    /// full speed.
+    pub fn scaled(self, speed: f64) -> Self {
+        Self {
+            max_velocity: self.max_velocity * speed,
+            ..self
+        }
+    }
+}
+
+/// Where a profile is at one moment.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ProfileState {
+    pub position: f64,
+    pub velocity: f64,
+    pub acceleration: f64,
+}
+
+/// Speeding up from rest to a velocity, or played backwards, slowing down from it to rest.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Ramp {
+    velocity: f64,
+
+    // how long acceleration takes to build up (and later to drop off), and how long it's held
+    jerk_time: f64,
+    accel_time: f64,
+
+    // the acceleration that's held, and how fast it builds up
+    acceleration: f64,
+    jerk: f64,
+}
+
+impl Ramp {
+    fn new(velocity: f64, limits: Limits) -> Self {
+        let max_acceleration = limits.max_acceleration;
+        match limits.max_jerk {
+            // too slow to reach max acceleration before it has to drop off again
+            Some(jerk) if velocity * jerk < max_acceleration.powi(2) => {
+                let jerk_time = (velocity / jerk).sqrt();
+                Self {
+                    velocity,
+                    jerk_time,
+                    accel_time: 0.0,
+                    acceleration: jerk * jerk_time,
+                    jerk,
+                }
+            }
+            Some(jerk) => Self {
+                velocity,
+                jerk_time: max_acceleration / jerk,
+                accel_time: velocity / max_acceleration - max_acceleration / jerk,
+                acceleration: max_acceleration,
+                jerk,
+            },
+            None => Self {
+                velocity,
+                jerk_time: 0.0,
+                accel_time: velocity / max_acceleration,
+                acceleration: max_acceleration,
+                jerk: 0.0,
+            },
+        }
+    }
+
+    fn duration(&self) -> f64 {
+        2.0 * self.jerk_time + self.accel_time
+    }
+
+    /// Distance covered while ramping. Velocity rises symmetrically, so the average is half the
+    /// final velocity.
+    fn distance(&self) -> f64 {
+        self.velocity * self.duration() / 2.0
+    }
+
+    fn sample(&self, time: f64) -> ProfileState {
+        let Self {
+            jerk_time,
+            accel_time,
+            acceleration,
+            jerk,
+            ..
+        } = *self;
+
+        if time >= self.duration() {
+            return ProfileState {
+                position: self.distance(),
+                velocity: self.velocity,
+                acceleration: 0.0,
+            };
+        }
+
+        // acceleration building up
+        if time < jerk_time {
+            return ProfileState {
+                position: jerk * time.powi(3) / 6.0,
+                velocity: jerk * time.powi(2) / 2.0,
+                acceleration: jerk * time,
+            };
+        }
+        let position = jerk * jerk_time.powi(3) / 6.0;
+        let velocity = jerk * jerk_time.powi(2) / 2.0;
+
+        // acceleration held
+        let time = time - jerk_time;
+        if time < accel_time {
+            return ProfileState {
+                position: position + velocity * time + acceleration * time.powi(2) / 2.0,
+                velocity: velocity + acceleration * time,
+                acceleration,
+            };
+        }
+        let position = position + velocity * accel_time + acceleration * accel_time.powi(2) / 2.0;
+        let velocity = velocity + acceleration * accel_time;
+
+        // acceleration dropping off
+        let time = time - accel_time;
+        ProfileState {
+            position: position + velocity * time + acceleration * time.powi(2) / 2.0
+                - jerk * time.powi(3) / 6.0,
+            velocity: velocity + acceleration * time - jerk * time.powi(2) / 2.0,
+            acceleration: acceleration - jerk * time,
+        }
+    }
+}
+
+/// A profile for moving a set distance, starting and ending at rest.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MotionProfile {
+    distance: f64,
+    ramp: Ramp,
+    cruise_time: f64,
+}
+
+impl MotionProfile {
+    /// Generates the fastest profile that moves `distance` (backwards if it's negative) within
+    /// `limits`.
+    pub fn new(distance: f64, limits: Limits) -> Self {
+        let length = distance.abs();
+
+        let full_speed = Ramp::new(limits.max_velocity, limits);
+        let ramp = if 2.0 * full_speed.distance() <= length {
+            full_speed
+        } else {
+            // too short to reach max velocity, so find the fastest it can get before having to
+            // slow down again
+            let (mut low, mut high) = (0.0, limits.max_velocity);
+            for _ in 0..50 {
+                let middle = (low + high) / 2.0;
+                if 2.0 * Ramp::new(middle, limits).distance() <= length {
+                    low = middle;
+                } else {
+                    high = middle;
+                }
+            }
+            Ramp::new(low, limits)
+        };
+
+        let cruise_time = if ramp.velocity > 0.0 {
+            (length - 2.0 * ramp.distance()).max(0.0) / ramp.velocity
+        } else {
+            0.0
+        };
+
+        Self {
+            distance,
+            ramp,
+            cruise_time,
+        }
+    }
+
+    /// How far the profile moves.
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// How long the profile takes.
+    pub fn duration(&self) -> f64 {
+        2.0 * self.ramp.duration() + self.cruise_time
+    }
+
+    /// Where the profile is `time` seconds in. Before the start it's at rest at the start, and
+    /// after the end it's at rest at the end.
+    pub fn sample(&self, time: f64) -> ProfileState {
+        let duration = self.duration();
+        if time < 0.0 {
+            return ProfileState::default();
+        } else if time >= duration {
+            // the last instant of a trapezoid is still slowing down, which would leave
+            // feedforward braking after the robot has stopped
+            return ProfileState {
+                position: self.distance,
+                ..Default::default()
+            };
+        }
+        let ramp_time = self.ramp.duration();
+
+        let state = if time < ramp_time {
+            self.ramp.sample(time)
+        } else if time < ramp_time + self.cruise_time {
+            ProfileState {
+                position: self.ramp.distance() + self.ramp.velocity * (time - ramp_time),
+                velocity: self.ramp.velocity,
+                acceleration: 0.0,
+            }
+        } else {
+            // slowing down is speeding up played backwards from the end
+            let remaining = self.ramp.sample(duration - time);
+            ProfileState {
+                position: self.distance.abs() - remaining.position,
+                velocity: remaining.velocity,
+                acceleration: -remaining.acceleration,
+            }
+        };
+
+        let sign = self.distance.signum();
+        ProfileState {
+            position: state.position * sign,
+            velocity: state.velocity * sign,
+            acceleration: state.acceleration * sign,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRAPEZOID: Limits = Limits {
+        max_velocity: 60.0,
+        max_acceleration: 120.0,
+        max_jerk: None,
+    };
+    const S_CURVE: Limits = Limits {
+        max_jerk: Some(600.0),
+        ..TRAPEZOID
+    };
+
+    const DT: f64 = 0.001;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-6,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    /// Samples `profile` every [`DT`] from its start to a little past its end.
+    fn samples(profile: &MotionProfile) -> Vec<ProfileState> {
+        let steps = (profile.duration() / DT).ceil() as usize + 10;
+        (0..=steps)
+            .map(|step| profile.sample(step as f64 * DT))
+            .collect()
+    }
+
+    #[test]
+    fn profiles_end_at_distance_and_at_rest() {
+        for limits in [TRAPEZOID, S_CURVE] {
+            for distance in [48.0, -48.0, 3.0, -0.5] {
+                let profile = MotionProfile::new(distance, limits);
+                let start = profile.sample(0.0);
+                let end = profile.sample(profile.duration());
+
+                assert_close(start.position, 0.0);
+                assert_close(start.velocity, 0.0);
+                assert_close(end.position, distance);
+                assert_close(end.velocity, 0.0);
+                assert_close(end.acceleration, 0.0);
+                assert_eq!(profile.sample(profile.duration() + 1.0), end);
+            }
+        }
+    }
+
+    #[test]
+    fn limits_are_respected() {
+        for limits in [TRAPEZOID, S_CURVE] {
+            for distance in [48.0, -48.0, 3.0] {
+                let samples = samples(&MotionProfile::new(distance, limits));
+
+                for state in &samples {
+                    assert!(state.velocity.abs() <= limits.max_velocity + 1e-9);
+                    assert!(state.acceleration.abs() <= limits.max_acceleration + 1e-9);
+                    assert!(state.velocity * distance >= -1e-9, "moved backwards");
+                }
+
+                for pair in samples.windows(2) {
+                    // position has to follow velocity, or the profile is jumping somewhere
+                    let velocity = (pair[1].position - pair[0].position) / DT;
+                    assert!((velocity - pair[0].velocity).abs() <= limits.max_acceleration * DT);
+
+                    if let Some(max_jerk) = limits.max_jerk {
+                        let jerk = (pair[1].acceleration - pair[0].acceleration) / DT;
+                        assert!(jerk.abs() <= max_jerk + 1e-6, "jerk {jerk} over the limit");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn short_profiles_never_reach_cruise_speed() {
+        // a trapezoid that speeds up then immediately slows down covers v² / a
+        let profile = MotionProfile::new(3.0, TRAPEZOID);
+        let peak = (TRAPEZOID.max_acceleration * 3.0).sqrt();
+        assert!(peak < TRAPEZOID.max_velocity);
+        assert_close(profile.sample(profile.duration() / 2.0).velocity, peak);
+        assert!(profile.cruise_time < 1e-6);
+
+        let profile = MotionProfile::new(3.0, S_CURVE);
+        let fastest = samples(&profile)
+            .iter()
+            .map(|state| state.velocity)
+            .fold(0.0, f64::max);
+        assert!(fastest < S_CURVE.max_velocity);
+        assert!(profile.cruise_time < 1e-6);
+        assert_close(profile.sample(profile.duration()).position, 3.0);
+    }
+
+    #[test]
+    fn zero_speed_and_distance_finish_immediately() {
+        for limits in [TRAPEZOID, S_CURVE] {
+            for profile in [
+                MotionProfile::new(24.0, limits.scaled(0.0)),
+                MotionProfile::new(0.0, limits),
+                MotionProfile::new(0.0, limits.scaled(0.0)),
+            ] {
+                assert_eq!(profile.duration(), 0.0);
+
+                for time in [-1.0, 0.0, 1.0] {
+                    let state = profile.sample(time);
+                    assert!(state.position.is_finite());
+                    assert_eq!(state.velocity, 0.0);
+                    assert_eq!(state.acceleration, 0.0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use evian::{
+    control::loops::{AngularPid, Feedback, Pid},
+    drivetrain::model::Arcade,
+    math::Angle,
+    prelude::*,
+};
+use log::warn;
+use vexide::time::sleep;
+
+use crate::{ekf::wrap, profile::MotionProfile, steering::turn_power};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Gains that turn a profile's velocity and acceleration into power, before any feedback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Feedforward {
+    /// Power needed to get moving at all, against friction.
+    pub ks: f64,
+
+    /// Power per unit of velocity, about one over the free speed.
+    pub kv: f64,
+
+    /// Power per unit of acceleration.
+    pub ka: f64,
+}
+
+impl Feedforward {
+    /// Power to move at `velocity` while accelerating at `acceleration`.
+    pub fn power(&self, velocity: f64, acceleration: f64) -> f64 {
+        let static_power = if velocity == 0.0 {
+            0.0
+        } else {
+            self.ks * velocity.signum()
+        };
+        static_power + self.kv * velocity + self.ka * acceleration
+    }
+}
+
+/// Profiled Motion
+///
+/// Drives straight or turns in place by following a [`MotionProfile`]. Each update the
+/// feedforward gives the power the profile's velocity and acceleration should take, and the PID
+/// corrects for how far the robot is from where the profile says it should be, so the motion takes
+/// the same time and ends in the same place every run.
+///
+/// Once the profile is over the motion keeps correcting until the robot settles within its
+/// tolerances.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfiledMotion {
+    /// Feedback on distance travelled.
+    pub linear_controller: Pid,
+
+    /// Feedback on heading.
+    pub angular_controller: AngularPid,
+
+    pub linear_tolerances: Tolerances,
+    pub angular_tolerances: Tolerances,
+
+    /// Feedforward for drives, in inches.
+    pub linear_feedforward: Feedforward,
+
+    /// Feedforward for turns, in radians.
+    pub angular_feedforward: Feedforward,
+
+    /// How long the motion can take before giving up.
+    pub timeout: Option<Duration>,
+}
+
+impl ProfiledMotion {
+    /// Drives straight for the profile's distance in inches, holding the heading the robot started
+    /// at.
+    pub async fn drive<M: Arcade, T: TracksForwardTravel + TracksHeading + TracksVelocity>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        profile: &MotionProfile,
+    ) {
+        let mut linear_controller = self.linear_controller;
+        let mut angular_controller = self.angular_controller;
+        let mut linear_tolerances = self.linear_tolerances;
+
+        let start_travel = drivetrain.tracking.forward_travel();
+        let start_heading = drivetrain.tracking.heading();
+        let start_time = Instant::now();
+        let mut prev_time = start_time;
+
+        loop {
+            sleep(UPDATE_INTERVAL).await;
+            let now = Instant::now();
+            let dt = now - prev_time;
+            prev_time = now;
+            let elapsed = (now - start_time).as_secs_f64();
+
+            let travel = drivetrain.tracking.forward_travel() - start_travel;
+            let error = profile.distance() - travel;
+            let velocity = drivetrain.tracking.linear_velocity();
+
+            if elapsed >= profile.duration() && linear_tolerances.check(error, velocity) {
+                break;
+            }
+            if self.timed_out(start_time) {
+                warn!("profiled drive timed out {error:.1} in from the end");
+                break;
+            }
+
+            let setpoint = profile.sample(elapsed);
+            let linear = self
+                .linear_feedforward
+                .power(setpoint.velocity, setpoint.acceleration)
+                + linear_controller.update(travel, setpoint.position, dt);
+            let angular = turn_power(angular_controller.update(
+                drivetrain.tracking.heading(),
+                start_heading,
+                dt,
+            ));
+
+            _ = drivetrain
+                .model
+                .drive_arcade(linear.clamp(-1.0, 1.0), angular);
+        }
+
+        _ = drivetrain.model.drive_arcade(0.0, 0.0);
+    }
+
+    /// Turns in place by the profile's distance in radians, counterclockwise if it's positive.
+    pub async fn turn<M: Arcade, T: TracksHeading + TracksVelocity>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        profile: &MotionProfile,
+    ) {
+        let mut angular_controller = self.angular_controller;
+        let mut angular_tolerances = self.angular_tolerances;
+
+        let start_heading = drivetrain.tracking.heading().as_radians();
+        let target = start_heading + profile.distance();
+        let start_time = Instant::now();
+        let mut prev_time = start_time;
+
+        loop {
+            sleep(UPDATE_INTERVAL).await;
+            let now = Instant::now();
+            let dt = now - prev_time;
+            prev_time = now;
+            let elapsed = (now - start_time).as_secs_f64();
+
+            let heading = drivetrain.tracking.heading();
+            let error = wrap(target - heading.as_radians());
+            let velocity = drivetrain.tracking.angular_velocity();
+
+            if elapsed >= profile.duration() && angular_tolerances.check(error, velocity) {
+                break;
+            }
+            if self.timed_out(start_time) {
+                warn!(
+                    "profiled turn timed out {:.1} deg from the end",
+                    error.to_degrees()
+                );
+                break;
+            }
+
+            let setpoint = profile.sample(elapsed);
+            let angular = self
+                .angular_feedforward
+                .power(setpoint.velocity, setpoint.acceleration)
+                + angular_controller.update(
+                    heading,
+                    Angle::from_radians(start_heading + setpoint.position),
+                    dt,
+                );
+
+            _ = drivetrain
+                .model
+                .drive_arcade(0.0, turn_power(angular.clamp(-1.0, 1.0)));
+        }
+
+        _ = drivetrain.model.drive_arcade(0.0, 0.0);
+    }
+
+    fn timed_out(&self, start_time: Instant) -> bool {
+        self.timeout
+            .is_some_and(|timeout| start_time.elapsed() > timeout)
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    f64::consts::PI,
+    time::{Duration, Instant},
+};
+
+use evian::{drivetrain::model::Arcade, prelude::*};
+use log::warn;
+use vexide::time::sleep;
+
+use crate::path::PathPoint;
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Pure Pursuit
+///
+/// Follows a sampled path by repeatedly steering along the arc that reaches a point a lookahead
+/// distance further along it. Unlike turning in place and then driving straight, the robot never
+/// has to stop at a corner, so curved paths (and chains of straight ones) are driven in one smooth
+/// motion.
+///
+/// The lookahead grows with speed, between [`PurePursuit::min_lookahead`] and
+/// [`PurePursuit::max_lookahead`]. A short lookahead follows the path closely but weaves, and a
+/// long one is smooth but cuts corners, so fast driving gets the smooth one.
+///
+/// Each point's speed limit caps the linear power while the robot is closest to it, and the
+/// robot slows down over the last [`PurePursuit::slowdown_distance`] inches so that it doesn't
+/// overshoot the end.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PurePursuit {
+    /// Shortest lookahead distance, used when the robot is stopped.
+    pub min_lookahead: f64,
+
+    /// Longest lookahead distance.
+    pub max_lookahead: f64,
+
+    /// How many seconds of driving at the current speed to look ahead.
+    pub lookahead_time: f64,
+
+    /// Distance between the left and right drive wheels, used to turn curvature into steering.
+    pub track_width: f64,
+
+    /// Distance from the end of the path at which the robot starts slowing down.
+    pub slowdown_distance: f64,
+
+    /// Slowest fraction of the speed limit the robot slows down to, so that it doesn't stall short
+    /// of the end.
+    pub min_speed: f64,
+
+    /// How close to the end of the path the robot needs to get to finish.
+    pub end_tolerance: f64,
+
+    /// How long the robot can spend following before giving up.
+    pub timeout: Option<Duration>,
+}
+
+impl PurePursuit {
+    /// Drives along `path`, facing backwards if `reverse` is set.
+    pub async fn follow<M: Arcade, T: TracksPosition + TracksHeading + TracksVelocity>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        path: &[PathPoint],
+        reverse: bool,
+    ) {
+        let Some(end) = path.last().copied() else {
+            return;
+        };
+        let start_time = Instant::now();
+        let mut closest = 0;
+
+        loop {
+            let position = drivetrain.tracking.position();
+            let distance_to =
+                |point: &PathPoint| (point.x - position.x).hypot(point.y - position.y);
+
+            let to_end = distance_to(&end);
+            if to_end < self.end_tolerance {
+                break;
+            }
+            if self
+                .timeout
+                .is_some_and(|timeout| start_time.elapsed() > timeout)
+            {
+                warn!("pure pursuit timed out {to_end:.1} in from the end of the path");
+                break;
+            }
+
+            // only search forwards, so that the robot never heads back to a part it already drove
+            closest = (closest..path.len())
+                .min_by(|&a, &b| distance_to(&path[a]).total_cmp(&distance_to(&path[b])))
+                .unwrap_or(closest);
+
+            let lookahead = (self.lookahead_time * drivetrain.tracking.linear_velocity().abs())
+                .clamp(self.min_lookahead, self.max_lookahead);
+            let target = path[closest..]
+                .iter()
+                .find(|point| distance_to(point) >= lookahead)
+                .unwrap_or(&end);
+
+            // driving backwards is driving forwards with the robot turned around
+            let mut heading = drivetrain.tracking.heading().as_radians();
+            if reverse {
+                heading += PI;
+            }
+
+            // curvature of the arc through the target that's tangent to the robot's heading
+            let (sin, cos) = heading.sin_cos();
+            let dx = target.x - position.x;
+            let dy = target.y - position.y;
+            let lateral = dy * cos - dx * sin;
+            let curvature = 2.0 * lateral / (dx * dx + dy * dy).max(f64::EPSILON);
+
+            let speed =
+                path[closest].speed * (to_end / self.slowdown_distance).clamp(self.min_speed, 1.0);
+            let linear = if reverse { -speed } else { speed };
+            // positive curvature is counterclockwise, and positive steering is clockwise
+            let steer = -speed * curvature * self.track_width / 2.0;
+
+            // keep the ratio between linear and angular power, so the arc is the same even when
+            // the turn is too tight to drive at full speed
+            let scale = 1.0 / (linear.abs() + steer.abs()).max(1.0);
+            _ = drivetrain.model.drive_arcade(linear * scale, steer * scale);
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+
+        _ = drivetrain.model.drive_arcade(0.0, 0.0);
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+use evian::{drivetrain::model::Arcade, prelude::*};
+use vexide::time::sleep;
+
+use crate::{ekf::wrap, trajectory::Trajectory};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// RAMSETE Controller
+///
+/// Tracks a [`Trajectory`] by driving at its velocities and correcting for wherever odometry says
+/// the robot has drifted from it. Because the trajectory already accounts for how fast the robot
+/// can accelerate and turn, the robot drives it in about the time it was generated for, which
+/// makes routes built from trajectories repeatable.
+///
+/// Velocities are turned into power with a single feedforward gain, so
+/// [`Ramsete::velocity_power`] should be about one over the drivetrain's free speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ramsete {
+    /// How aggressively position error is corrected, like a proportional gain. In inches, so about
+    /// 1/1550th of the usual 2.0 for meters.
+    pub b: f64,
+
+    /// Damping, between 0 and 1.
+    pub zeta: f64,
+
+    /// Distance between the left and right drive wheels.
+    pub track_width: f64,
+
+    /// Power per inch per second of wheel velocity.
+    pub velocity_power: f64,
+}
+
+impl Ramsete {
+    /// Drives `trajectory` from start to end.
+    pub async fn follow<M: Arcade, T: TracksPosition + TracksHeading>(
+        &self,
+        drivetrain: &mut Drivetrain<M, T>,
+        trajectory: &Trajectory,
+    ) {
+        let start_time = Instant::now();
+
+        loop {
+            let time = start_time.elapsed().as_secs_f64();
+            if time > trajectory.duration() {
+                break;
+            }
+
+            let reference = trajectory.sample(time);
+            let position = drivetrain.tracking.position();
+            let heading = drivetrain.tracking.heading().as_radians();
+
+            // error in the robot's frame
+            let (sin, cos) = heading.sin_cos();
+            let dx = reference.x - position.x;
+            let dy = reference.y - position.y;
+            let forward_error = dx * cos + dy * sin;
+            let lateral_error = dy * cos - dx * sin;
+            let heading_error = wrap(reference.heading - heading);
+
+            let gain = 2.0
+                * self.zeta
+                * (reference.angular_velocity.powi(2) + self.b * reference.velocity.powi(2)).sqrt();
+            let sinc = if heading_error.abs() < 1e-6 {
+                1.0
+            } else {
+                heading_error.sin() / heading_error
+            };
+
+            let velocity = reference.velocity * heading_error.cos() + gain * forward_error;
+            let angular_velocity = reference.angular_velocity
+                + gain * heading_error
+                + self.b * reference.velocity * sinc * lateral_error;
+
+            // positive angular velocity is counterclockwise, and positive steering is clockwise
+            _ = drivetrain.model.drive_arcade(
+                velocity * self.velocity_power,
+                -angular_velocity * self.track_width / 2.0 * self.velocity_power,
+            );
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+
+        _ = drivetrain.model.drive_arcade(0.0, 0.0);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{f64::consts::FRAC_PI_2, rc::Rc};
+
+use evian::{
+    math::{Angle, Vec2},
+    prelude::*,
+};
+use log::{info, warn};
+use vexide::{prelude::*, smart::SmartPort};
+
+use crate::{field::WALL, fused_tracking::FusedTracking};
+
+/// Readings below this confidence are ignored.
+const MIN_CONFIDENCE: f64 = 0.5;
+
+const MM_PER_INCH: f64 = 25.4;
+
+/// A distance sensor mounted on the robot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceMount {
+    /// Smart port number the sensor is plugged into.
+    pub port: u8,
+
+    /// Position of the sensor relative to the robot's center of rotation, with x forwards and y to
+    /// the left.
+    pub offset: Vec2<f64>,
+
+    /// Direction the sensor faces relative to the front of the robot, counterclockwise-positive.
+    pub angle: Angle,
+}
+
+/// A distance sensor along with where it's mounted.
+pub struct MountedSensor {
+    pub mount: DistanceMount,
+    pub sensor: DistanceSensor,
+}
+
+impl MountedSensor {
+    /// Distance to the object in front of the sensor in inches.
+    ///
+    /// Returns `None` if nothing is in range or the reading isn't confident enough to use.
+    pub fn distance(&self) -> Option<f64> {
+        match self.sensor.object() {
+            Ok(Some(object)) if object.confidence >= MIN_CONFIDENCE => {
+                Some(f64::from(object.distance) / MM_PER_INCH)
+            }
+            Ok(_) => None,
+            Err(e) => {
+                warn!(
+                    "couldn't read distance sensor on port {}: {e}",
+                    self.mount.port
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Distance sensors shared between everything that localizes with them.
+pub type DistanceSensors = Rc<[MountedSensor]>;
+
+/// Sets up a distance sensor for every mount that's fitted.
+///
+/// # Safety
+///
+/// None of the mounts' ports may be used anywhere else in the program.
+pub unsafe fn mount_sensors(mounts: &[Option<DistanceMount>]) -> DistanceSensors {
+    mounts
+        .iter()
+        .flatten()
+        .map(|mount| MountedSensor {
+            mount: *mount,
+            sensor: DistanceSensor::new(unsafe { SmartPort::new(mount.port) }),
+        })
+        .collect()
+}
+
+/// Wall-Distance Relocalization
+///
+/// Odometry drifts over the course of a route, and the drive wheels slip every time the robot
+/// pushes into a goal or matchloader. The perimeter walls don't move, so whenever one of the
+/// distance sensors is pointed roughly square at a wall, its reading pins down one coordinate of
+/// the robot's position exactly.
+///
+/// Readings that disagree with odometry by more than [`Relocalizer::max_correction`] are assumed
+/// to have hit a game element or another robot instead of the wall, and are ignored.
+pub struct Relocalizer {
+    sensors: DistanceSensors,
+
+    /// How far from square a sensor can be to a wall for its reading to be used.
+    pub square_tolerance: Angle,
+
+    /// Largest correction to a coordinate that will be trusted.
+    pub max_correction: f64,
+}
+
+impl Relocalizer {
+    /// Constructs a [`Relocalizer`] using the provided distance sensors.
+    pub fn new(sensors: DistanceSensors, square_tolerance: Angle, max_correction: f64) -> Self {
+        Self {
+            sensors,
+            square_tolerance,
+            max_correction,
+        }
+    }
+
+    /// Corrects the tracked position using any sensors that are square to a wall.
+    ///
+    /// Returns `true` if either coordinate was corrected.
+    pub fn relocalize(&self, tracking: &mut FusedTracking) -> bool {
+        let position = tracking.position();
+        let heading = tracking.heading();
+
+        let mut x = Vec::new();
+        let mut y = Vec::new();
+
+        for sensor in self.sensors.iter() {
+            let mount = sensor.mount;
+
+            // snap the direction the sensor faces on the field to the nearest wall
+            let direction = (heading + mount.angle).wrapped_positive().as_radians();
+            let wall = (direction / FRAC_PI_2).round();
+            let skew = direction - wall * FRAC_PI_2;
+            if skew.abs() > self.square_tolerance.as_radians() {
+                continue;
+            }
+
+            let Some(distance) = sensor.distance() else {
+                continue;
+            };
+            let distance = distance * skew.cos();
+            let offset = Vec2::new(
+                mount.offset.x * heading.cos() - mount.offset.y * heading.sin(),
+                mount.offset.x * heading.sin() + mount.offset.y * heading.cos(),
+            );
+
+            let (estimates, current, corrected) = match wall as u8 % 4 {
+                0 => (&mut x, position.x, WALL - distance - offset.x),
+                1 => (&mut y, position.y, WALL - distance - offset.y),
+                2 => (&mut x, position.x, -WALL + distance - offset.x),
+                _ => (&mut y, position.y, -WALL + distance - offset.y),
+            };
+
+            if (corrected - current).abs() > self.max_correction {
+                continue;
+            }
+            estimates.push(corrected);
+        }
+
+        if x.is_empty() && y.is_empty() {
+            return false;
+        }
+
+        let average = |estimates: &[f64], current: f64| {
+            if estimates.is_empty() {
+                current
+            } else {
+                estimates.iter().sum::<f64>() / estimates.len() as f64
+            }
+        };
+        let corrected = Vec2::new(average(&x, position.x), average(&y, position.y));
+
+        info!(
+            "relocalized from ({:.2}, {:.2}) to ({:.2}, {:.2})",
+            position.x, position.y, corrected.x, corrected.y
+        );
+        tracking.set_position(corrected);
+        true
+    }
+}
@@ -0,0 +1,33 @@
+use std::f64::consts::TAU;
+
+/// A small xorshift random number generator, so that anything random (like particle filters) is
+/// reproducible and doesn't need any dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck at zero
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A uniformly distributed number in `[0, 1)`.
+    pub fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A normally distributed number with the given standard deviation.
+    pub fn gaussian(&mut self, std_dev: f64) -> f64 {
+        // box-muller transform
+        let u1 = 1.0 - self.uniform();
+        let u2 = self.uniform();
+        std_dev * (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+    }
+}
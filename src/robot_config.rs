@@ -0,0 +1,151 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use log::{info, warn};
+
+use crate::{drive_config::DriveConfig, tracking_config::TrackingWheelConfig};
+
+/// Robot Measurements
+///
+/// Physical measurements that the calibration routines fit, saved to the SD card so they can be
+/// re-measured without editing `consts.rs`. Anything missing from the file falls back to the
+/// nominal values in `consts.rs`, so deleting the file undoes every calibration.
+///
+/// The file is a list of `key = value` lines, where lines starting with `#` are ignored:
+///
+/// ```text
+/// wheel_diameter = 3.2914
+/// track_width = 11.8127
+/// tracking_wheel_diameter = 2.0306
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RobotConfig {
+    /// Effective diameter of the drive wheels.
+    pub wheel_diameter: f64,
+
+    /// Effective distance between the left and right drive wheels.
+    pub track_width: f64,
+
+    /// Effective diameter of the dedicated tracking wheels.
+    pub tracking_wheel_diameter: f64,
+}
+
+#[derive(Debug)]
+enum ParseError {
+    /// A line isn't `key = value`.
+    Syntax(usize),
+
+    /// A value isn't a positive number.
+    Value(usize),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(line) => write!(f, "line {line} isn't `key = value`"),
+            Self::Value(line) => write!(f, "line {line} doesn't have a positive number"),
+        }
+    }
+}
+
+impl RobotConfig {
+    /// Loads the measurements saved at `path`, using `defaults` for any that aren't saved.
+    ///
+    /// A missing or unreadable file is logged and `defaults` is returned, so a bad SD card never
+    /// stops the robot from booting.
+    pub fn load(path: &str, defaults: Self) -> Self {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                info!("no robot config at {path}, using nominal measurements");
+                return defaults;
+            }
+            Err(e) => {
+                warn!("couldn't read robot config at {path}, using nominal measurements: {e}");
+                return defaults;
+            }
+        };
+
+        match Self::parse(&text, defaults) {
+            Ok(config) => {
+                info!("loaded robot config from {path}: {config:?}");
+                config
+            }
+            Err(e) => {
+                warn!("couldn't parse robot config at {path}, using nominal measurements: {e}");
+                defaults
+            }
+        }
+    }
+
+    /// Parses a config file, using `defaults` for any measurements it doesn't have.
+    ///
+    /// Unknown keys are logged and skipped so that files written by newer code still load.
+    fn parse(text: &str, defaults: Self) -> Result<Self, ParseError> {
+        let mut config = defaults;
+
+        for (number, line) in text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+        {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or(ParseError::Syntax(number))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite() && *value > 0.0)
+                .ok_or(ParseError::Value(number))?;
+
+            match key.trim() {
+                "wheel_diameter" => config.wheel_diameter = value,
+                "track_width" => config.track_width = value,
+                "tracking_wheel_diameter" => config.tracking_wheel_diameter = value,
+                key => warn!("unknown robot config key `{key}` on line {number}"),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Writes every measurement to `path`, replacing whatever was there.
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "# written by the calibration routines, delete this file to use the values in consts.rs"
+        )?;
+        writeln!(file, "wheel_diameter = {:.4}", self.wheel_diameter)?;
+        writeln!(file, "track_width = {:.4}", self.track_width)?;
+        writeln!(
+            file,
+            "tracking_wheel_diameter = {:.4}",
+            self.tracking_wheel_diameter
+        )?;
+        file.flush()
+    }
+
+    /// Returns `drive` with its measurements replaced by these.
+    pub fn apply_drive<const N: usize>(&self, drive: DriveConfig<N>) -> DriveConfig<N> {
+        DriveConfig {
+            wheel_diameter: self.wheel_diameter,
+            track_width: self.track_width,
+            ..drive
+        }
+    }
+
+    /// Returns `wheel` with its diameter replaced by the tracking wheel diameter.
+    pub fn apply_tracking(&self, wheel: TrackingWheelConfig) -> TrackingWheelConfig {
+        TrackingWheelConfig {
+            diameter: self.tracking_wheel_diameter,
+            ..wheel
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fs, future::Future, io, pin::Pin, rc::Rc};
+
+use evian::{math::Angle, prelude::*};
+use log::{error, info, warn};
+use vexide::time::sleep;
+
+use crate::{
+    Jodio, auton,
+    intake::Command,
+    jerryio::{self, ACTIONS_EXTENSION, Action, Control, IntakeAction, Route, Statement},
+    placement::{MAX_ROUTES, PlacedRoute, StartPose},
+};
+
+/// A route function that `SimpleSelect` can call.
+type RouteFn = for<'a> fn(&'a mut Jodio) -> Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// What the selector shows for a slot that no route file on the SD card was loaded into.
+const EMPTY_SLOT: &str = "No SD Route";
+
+/// A route file from `routes/` that was checked and embedded by `build.rs`.
+pub struct EmbeddedRoute {
+    /// The file name without `.txt`.
+    pub name: &'static str,
+
+    pub text: &'static str,
+
+    /// The route's actions file, if it has one.
+    pub actions: Option<&'static str>,
+
+    /// Where the route's JerryIO path starts.
+    pub start: Option<StartPose>,
+}
+
+include!(concat!(env!("OUT_DIR"), "/routes.rs"));
+
+/// Runs a route embedded from `routes/`, starting from wherever its path starts.
+pub async fn run_embedded(jodio: &mut Jodio, route: &EmbeddedRoute) {
+    // build.rs already checked that this loads
+    match jerryio::load(route.text, route.actions) {
+        Ok(loaded) => run_file(jodio, route.name, route.start, &loaded).await,
+        Err(e) => error!("couldn't load {}: {e}", route.name),
+    }
+}
+
+/// A route file loaded from the SD card.
+pub struct SdRoute {
+    /// The file name without `.txt`.
+    pub name: String,
+
+    /// Where the route's JerryIO path starts.
+    pub start: Option<StartPose>,
+
+    route: Rc<Route>,
+}
+
+/// Loads every route file in `dir` on the SD card, along with its actions file if it has one, so
+/// routes can be changed without uploading the program again.
+///
+/// Returns the routes that loaded, sorted by name, and why each of the others didn't. A missing
+/// folder just means there are no routes on the card.
+pub fn load_sd(dir: &str) -> (Vec<SdRoute>, Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            info!("no {dir}/ folder on the sd card, only using built in routes");
+            return (Vec::new(), Vec::new());
+        }
+        Err(e) => {
+            warn!("couldn't read {dir}/ on the sd card: {e}");
+            return (Vec::new(), vec![format!("{dir}/: {e}")]);
+        }
+    };
+
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    let mut routes = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        let Some(name) = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+
+        let loaded = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| {
+                let actions = match fs::read_to_string(path.with_extension(ACTIONS_EXTENSION)) {
+                    Ok(actions) => Some(actions),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(format!("actions file: {e}")),
+                };
+                jerryio::load(&text, actions.as_deref()).map_err(|e| e.to_string())
+            });
+        let loaded = match loaded {
+            Ok(loaded) => Rc::new(loaded),
+            Err(e) => {
+                warn!("couldn't load route {}: {e}", path.display());
+                errors.push(format!("{name}: {e}"));
+                continue;
+            }
+        };
+
+        info!("loaded route {name} from the sd card");
+        let start = loaded
+            .start_pose()
+            .map(|(x, y, heading)| StartPose::new(x, y, Angle::from_degrees(heading)));
+        routes.push(SdRoute {
+            name,
+            start,
+            route: loaded,
+        });
+    }
+
+    (routes, errors)
+}
+
+/// Fills `slots` entries of the selector with the routes loaded from the SD card, in order.
+/// Slots without a route are left in the list, since `SimpleSelect` takes a fixed number of
+/// routes, and routes that don't get a slot are added to `errors`.
+///
+/// The selector needs names that live for the rest of the program, so each route's name is
+/// leaked once here.
+pub fn sd_slots(
+    routes: &[SdRoute],
+    slots: usize,
+    errors: &mut Vec<String>,
+) -> Vec<PlacedRoute<Jodio>> {
+    for route in routes.iter().skip(slots) {
+        warn!("no room in the selector for {}", route.name);
+        errors.push(format!("{}: no room in the selector", route.name));
+    }
+
+    (0..slots)
+        .map(|slot| {
+            let (name, start) = match routes.get(slot) {
+                Some(route) => (&*route.name.clone().leak(), route.start),
+                None => (EMPTY_SLOT, None),
+            };
+            PlacedRoute {
+                route: autons::simple::Route::new(name, SD_SLOTS[slot]),
+                start,
+            }
+        })
+        .collect()
+}
+
+/// One function per selector slot, since `SimpleSelect` routes are plain function pointers and
+/// can't carry which route file they run.
+const SD_SLOTS: [RouteFn; MAX_ROUTES] = [
+    run_slot::<0>,
+    run_slot::<1>,
+    run_slot::<2>,
+    run_slot::<3>,
+    run_slot::<4>,
+    run_slot::<5>,
+];
+
+fn run_slot<const SLOT: usize>(jodio: &mut Jodio) -> Pin<Box<dyn Future<Output = ()> + '_>> {
+    Box::pin(run_sd(jodio, SLOT))
+}
+
+/// Runs the route loaded from the SD card into `slot`.
+async fn run_sd(jodio: &mut Jodio, slot: usize) {
+    let Some(route) = jodio.sd_routes.get(slot) else {
+        warn!("no sd route in slot {slot}");
+        return;
+    };
+    let (name, start, route) = (route.name.clone(), route.start, route.route.clone());
+
+    run_file(jodio, &name, start, &route).await;
+}
+
+/// Places the robot where the route starts, then runs it with the localizer running and a trace
+/// being recorded.
+async fn run_file(jodio: &mut Jodio, name: &str, start: Option<StartPose>, route: &Route) {
+    let _trace = jodio.trace.start(name);
+    if let Some(start) = start {
+        auton::place(jodio, start);
+    }
+    jodio.localizer.start(&jodio.dt.tracking);
+
+    run(jodio, &route.statements, &route.waypoints()).await;
+
+    jodio.localizer.stop();
+}
+
+/// Runs a parsed JerryIO route.
+///
+/// Each motion is run through [`Motion`] with its speed limited to the statement's speed, and
+/// action hooks are run in place between them. A motion that times out or stalls is logged and the
+/// route carries on from wherever the robot ended up. Headings were already converted from
+/// JerryIO's compass bearings when the route was parsed, so they're used as they are.
+///
+/// `waypoints` are the path's end points from its JerryIO data (see [`Path::waypoints`]). JerryIO
+/// generates one drive per segment, so the end of each drive is recorded to the trace as the next
+/// waypoint. Pass an empty slice if there's no path data.
+///
+/// [`Motion`]: crate::motion::Motion
+/// [`Path::waypoints`]: crate::jerryio::Path::waypoints
+pub async fn run(jodio: &mut Jodio, statements: &[Statement], waypoints: &[Control]) {
+    // the first waypoint is where the path starts
+    let mut ends = waypoints.iter().skip(1);
+
+    for statement in statements {
+        match *statement {
+            Statement::Forward { distance, speed } => {
+                let result = jodio
+                    .motion
+                    .with_speed(speed / 100.0)
+                    .drive_distance(&mut jodio.dt, distance)
+                    .await;
+                if let Some(end) = ends.next() {
+                    jodio.trace.waypoint((end.x, end.y));
+                }
+                auton::recover(jodio, &result);
+            }
+            Statement::Backward { distance, speed } => {
+                let result = jodio
+                    .motion
+                    .with_speed(speed / 100.0)
+                    .drive_distance(&mut jodio.dt, -distance)
+                    .await;
+                if let Some(end) = ends.next() {
+                    jodio.trace.waypoint((end.x, end.y));
+                }
+                auton::recover(jodio, &result);
+            }
+            Statement::TurnTo { heading, speed } => {
+                let result = jodio
+                    .motion
+                    .with_turn_speed(speed / 100.0)
+                    .turn_to_heading(&mut jodio.dt, heading.deg())
+                    .await;
+                auton::recover(jodio, &result);
+            }
+            Statement::Action(action) => run_action(jodio, action).await,
+        }
+    }
+}
+
+async fn run_action(jodio: &mut Jodio, action: Action) {
+    match action {
+        Action::Intake(action) => jodio.intake_command.set(match action {
+            IntakeAction::Collect => Command::Collect,
+            IntakeAction::ScoreLong => Command::ScoreLong,
+            IntakeAction::ScoreMiddle => Command::ScoreMiddle,
+            IntakeAction::ScoreLow => Command::ScoreLow,
+            IntakeAction::Stop => Command::Stop,
+        }),
+        Action::ExtendMatchloader => jodio.matchloader.extend(),
+        Action::RetractMatchloader => jodio.matchloader.retract(),
+        Action::Localize => {
+            jodio.localizer.apply(&mut jodio.dt.tracking);
+        }
+        Action::DisableColorSort => jodio.allegiance.set(None),
+        Action::Wait(duration) => sleep(duration).await,
+    }
+}
@@ -0,0 +1,11 @@
+//! The sign convention between headings and turn power.
+//!
+//! Tracking headings, and so the output of any controller correcting one, are
+//! counterclockwise-positive. Arcade turn power is clockwise-positive, matching the driver's turn
+//! stick. Everything that steers with a heading controller goes through [`turn_power`], so the two
+//! are only reconciled here.
+
+/// Converts the output of a heading controller into arcade turn power.
+pub fn turn_power(controller_output: f64) -> f64 {
+    -controller_output
+}
@@ -0,0 +1,188 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+
+use evian::{
+    drivetrain::model::{Arcade, Tank},
+    math::Angle,
+};
+use log::{info, warn};
+use vexide::{prelude::*, task::Task};
+
+use crate::imu::ImuAxis;
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Which way the robot is tipping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TipDirection {
+    /// The front of the robot is lifting.
+    Backward,
+
+    /// The back of the robot is lifting.
+    Forward,
+
+    /// One side of the robot is lifting.
+    Sideways,
+}
+
+/// How far the robot is tilted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tilt {
+    Level,
+
+    /// Tilted enough that drive output is limited.
+    Leaning,
+
+    /// Tilted enough that the robot is about to fall over.
+    Tipping(TipDirection),
+}
+
+/// Tip Guard
+///
+/// A drivetrain model wrapper that watches the IMU's pitch and roll in the background, so that
+/// climbing onto a goal or a block can't flip the robot no matter what is commanding the
+/// drivetrain.
+///
+/// * Past the lean angle, both linear and angular power are scaled by
+///   [`TipGuard::lean_power_scale`].
+/// * Past the tip angle, commands are ignored. If the robot is tipping forwards or backwards it
+///   drives at [`TipGuard::recovery_power`] in the direction that brings it back down, and if it's
+///   tipping sideways the drivetrain is stopped.
+///
+/// Both arcade and tank commands are guarded, with tank commands split into linear and angular
+/// power first.
+pub struct TipGuard<M> {
+    /// The wrapped drivetrain model.
+    pub inner: M,
+
+    /// Multiplier applied to power while the robot is leaning.
+    pub lean_power_scale: f64,
+
+    /// Linear power used to drive back down while the robot is tipping.
+    pub recovery_power: f64,
+
+    tilt: Rc<Cell<Tilt>>,
+    _task: Task<()>,
+}
+
+impl<M> TipGuard<M> {
+    /// Wraps a drivetrain model with a tip guard.
+    ///
+    /// # Constants
+    ///
+    /// * `lean_angle` - Pitch or roll past which power is limited.
+    /// * `tip_angle` - Pitch or roll past which the robot is recovered.
+    /// * `lean_power_scale` - Multiplier applied to power while the robot is leaning.
+    /// * `recovery_power` - Linear power used to drive back down while the robot is tipping.
+    /// * `imu_axis` - Which of the IMU's axes points towards the front of the robot.
+    pub fn new(
+        inner: M,
+        imu: Rc<InertialSensor>,
+        lean_angle: Angle,
+        tip_angle: Angle,
+        lean_power_scale: f64,
+        recovery_power: f64,
+        imu_axis: ImuAxis,
+    ) -> Self {
+        let tilt = Rc::new(Cell::new(Tilt::Level));
+
+        Self {
+            inner,
+            lean_power_scale,
+            recovery_power,
+            _task: spawn(Self::task(
+                imu,
+                imu_axis,
+                tilt.clone(),
+                lean_angle,
+                tip_angle,
+            )),
+            tilt,
+        }
+    }
+
+    async fn task(
+        imu: Rc<InertialSensor>,
+        imu_axis: ImuAxis,
+        tilt: Rc<Cell<Tilt>>,
+        lean_angle: Angle,
+        tip_angle: Angle,
+    ) {
+        loop {
+            // a sensor that can't be read can't say the robot is tipping
+            let current = match imu_axis.tilt(&imu) {
+                Some((pitch, roll)) => {
+                    classify(pitch, roll, lean_angle.as_radians(), tip_angle.as_radians())
+                }
+                None => Tilt::Level,
+            };
+
+            let previous = tilt.replace(current);
+            if current != previous {
+                match current {
+                    Tilt::Tipping(direction) => warn!("tipping {direction:?}, recovering"),
+                    Tilt::Leaning => info!("leaning, limiting drive power"),
+                    Tilt::Level => info!("level again"),
+                }
+            }
+
+            sleep(UPDATE_INTERVAL).await;
+        }
+    }
+
+    /// Adjusts linear and angular power for how far the robot is currently tilted.
+    fn guard(&self, linear: f64, angular: f64) -> (f64, f64) {
+        match self.tilt.get() {
+            Tilt::Level => (linear, angular),
+            Tilt::Leaning => (
+                linear * self.lean_power_scale,
+                angular * self.lean_power_scale,
+            ),
+            // accelerating away from the side that's lifting pitches the robot back down
+            Tilt::Tipping(TipDirection::Backward) => (-self.recovery_power, 0.0),
+            Tilt::Tipping(TipDirection::Forward) => (self.recovery_power, 0.0),
+            Tilt::Tipping(TipDirection::Sideways) => (0.0, 0.0),
+        }
+    }
+
+    /// How far the robot is currently tilted.
+    pub fn tilt(&self) -> Tilt {
+        self.tilt.get()
+    }
+
+    /// Returns a handle that can read the tilt from other tasks.
+    pub fn tilt_reader(&self) -> Rc<Cell<Tilt>> {
+        self.tilt.clone()
+    }
+}
+
+fn classify(pitch: f64, roll: f64, lean_angle: f64, tip_angle: f64) -> Tilt {
+    if pitch > tip_angle {
+        Tilt::Tipping(TipDirection::Backward)
+    } else if pitch < -tip_angle {
+        Tilt::Tipping(TipDirection::Forward)
+    } else if roll.abs() > tip_angle {
+        Tilt::Tipping(TipDirection::Sideways)
+    } else if pitch.abs() > lean_angle || roll.abs() > lean_angle {
+        Tilt::Leaning
+    } else {
+        Tilt::Level
+    }
+}
+
+impl<M: Arcade> Arcade for TipGuard<M> {
+    type Error = M::Error;
+
+    fn drive_arcade(&mut self, throttle: f64, steer: f64) -> Result<(), Self::Error> {
+        let (linear, angular) = self.guard(throttle, steer);
+        self.inner.drive_arcade(linear, angular)
+    }
+}
+
+impl<M: Tank> Tank for TipGuard<M> {
+    type Error = M::Error;
+
+    fn drive_tank(&mut self, left: f64, right: f64) -> Result<(), Self::Error> {
+        let (linear, angular) = self.guard((left + right) / 2.0, (left - right) / 2.0);
+        self.inner.drive_tank(linear + angular, linear - angular)
+    }
+}
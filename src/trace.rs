@@ -0,0 +1,79 @@
+//! Odometry trace format.
+//!
+//! Traces are written as CSV so that they can be opened in a spreadsheet as well as plotted with
+//! `host/`'s `trace-plot`. The first line names the route, and every following line is either a
+//! `sample` of where the robot was or a `waypoint` the route planned to drive through:
+//!
+//! ```csv
+//! # route: skills
+//! kind,time,x,y,heading,linear_velocity,angular_velocity
+//! sample,0.020,-61.000,18.500,90.000,0.000,0.000
+//! waypoint,1.250,-22.801,22.432,,,
+//! ```
+//!
+//! Times are in seconds since the route started, positions in inches, headings in degrees, and
+//! velocities in inches and degrees per second.
+//!
+//! This module only depends on `std` so that it can be shared with `host/`.
+
+use std::io::{self, Write};
+
+/// The CSV header, after the route name line.
+pub const HEADER: &str = "kind,time,x,y,heading,linear_velocity,angular_velocity";
+
+/// Where the robot was at one point in time.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+    pub linear_velocity: f64,
+    pub angular_velocity: f64,
+}
+
+/// A point the route planned to drive through.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Waypoint {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Everything recorded during one route.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Trace {
+    pub route: String,
+    pub samples: Vec<Sample>,
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl Trace {
+    /// Writes the trace as CSV.
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "# route: {}", self.route)?;
+        writeln!(writer, "{HEADER}")?;
+
+        for sample in &self.samples {
+            writeln!(
+                writer,
+                "sample,{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}",
+                sample.time,
+                sample.x,
+                sample.y,
+                sample.heading,
+                sample.linear_velocity,
+                sample.angular_velocity
+            )?;
+        }
+        for waypoint in &self.waypoints {
+            writeln!(
+                writer,
+                "waypoint,{:.3},{:.3},{:.3},,,",
+                waypoint.time, waypoint.x, waypoint.y
+            )?;
+        }
+
+        writer.flush()
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::BufWriter,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use evian::math::Vec2;
+use log::{info, warn};
+use vexide::{prelude::*, task::Task};
+
+use crate::{
+    fused_tracking::TrackingReader,
+    trace::{Sample, Trace, Waypoint},
+};
+
+/// Trace Recorder
+///
+/// Samples the tracked pose and velocity at a fixed rate while a route runs, then writes
+/// everything to the SD card as `trace_<route>.csv` when the route is over. Routes can also mark
+/// the points they planned to drive through, so the trace shows where the robot was supposed to go
+/// alongside where it went.
+///
+/// Recording lasts as long as the [`Tracing`] returned by [`TraceRecorder::start`]. Routes hold on
+/// to it until they return, and when field control ends autonomous the route is dropped part way
+/// through along with it, so the trace is written and sampling stops either way.
+///
+/// Samples are kept in memory until then so that writing to the SD card can't stall the route.
+pub struct TraceRecorder {
+    state: Rc<RefCell<Recording>>,
+    _task: Task<()>,
+}
+
+#[derive(Default)]
+struct Recording {
+    trace: Trace,
+    start: Option<Instant>,
+}
+
+impl Recording {
+    fn elapsed(&self) -> Option<f64> {
+        self.start.map(|start| start.elapsed().as_secs_f64())
+    }
+}
+
+impl TraceRecorder {
+    /// Constructs a [`TraceRecorder`] that samples every `interval` while recording.
+    pub fn new(reader: TrackingReader, interval: Duration) -> Self {
+        let state = Rc::new(RefCell::new(Recording::default()));
+
+        Self {
+            _task: spawn(Self::task(state.clone(), reader, interval)),
+            state,
+        }
+    }
+
+    async fn task(state: Rc<RefCell<Recording>>, reader: TrackingReader, interval: Duration) {
+        loop {
+            {
+                let mut state = state.borrow_mut();
+                if let Some(time) = state.elapsed() {
+                    let pose = reader.pose();
+                    state.trace.samples.push(Sample {
+                        time,
+                        x: pose.x,
+                        y: pose.y,
+                        heading: pose.heading.to_degrees(),
+                        linear_velocity: reader.linear_velocity(),
+                        angular_velocity: reader.angular_velocity().to_degrees(),
+                    });
+                }
+            }
+
+            sleep(interval).await;
+        }
+    }
+
+    /// Starts a new trace for the named route, discarding any unfinished one. The trace is written
+    /// when the returned [`Tracing`] is dropped.
+    pub fn start(&mut self, route: &str) -> Tracing {
+        let start = Instant::now();
+        *self.state.borrow_mut() = Recording {
+            trace: Trace {
+                route: route.to_string(),
+                ..Default::default()
+            },
+            start: Some(start),
+        };
+
+        Tracing {
+            state: self.state.clone(),
+            start,
+        }
+    }
+
+    /// Marks a point the route plans to drive through.
+    pub fn waypoint(&mut self, point: impl Into<Vec2<f64>>) {
+        let point = point.into();
+        let mut state = self.state.borrow_mut();
+        if let Some(time) = state.elapsed() {
+            state.trace.waypoints.push(Waypoint {
+                time,
+                x: point.x,
+                y: point.y,
+            });
+        }
+    }
+}
+
+/// A trace being recorded by a [`TraceRecorder`]. Dropping it stops recording and writes the trace
+/// to the SD card.
+#[must_use = "the trace stops recording as soon as this is dropped"]
+pub struct Tracing {
+    state: Rc<RefCell<Recording>>,
+    start: Instant,
+}
+
+impl Drop for Tracing {
+    fn drop(&mut self) {
+        // a newer trace replaced this one, so it isn't this one's to write
+        if self.state.borrow().start != Some(self.start) {
+            return;
+        }
+        let recording = self.state.take();
+
+        let path = format!("trace_{}.csv", recording.trace.route);
+        match File::create(&path).and_then(|file| recording.trace.write_csv(BufWriter::new(file))) {
+            Ok(()) => info!(
+                "wrote {} trace samples to {path}",
+                recording.trace.samples.len()
+            ),
+            Err(e) => warn!("couldn't write trace to {path}: {e}"),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{cell::RefCell, rc::Rc};
+
+use evian::{
+    math::Angle,
+    tracking::{
+        sensor::RotarySensor,
+        wheeled::{TrackingWheel, WheeledTracking},
+    },
+};
+use log::{error, info, warn};
+use vexide::{
+    adi::{AdiPort, encoder::AdiEncoder},
+    prelude::*,
+    smart::{PortError, SmartPort},
+};
+
+use crate::{imu::SharedImu, ports::Ports};
+
+const ORIGIN: (f64, f64) = (0.0, 0.0);
+const HEADING: Angle = Angle::from_radians(0.0);
+
+/// Sensor that a dedicated tracking wheel is mounted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WheelSensor {
+    /// A V5 rotation sensor on a smart port.
+    Rotation { port: u8, direction: Direction },
+
+    /// A quadrature encoder on two adjacent ADI ports, where `top` is the odd port (1 for A, 3 for
+    /// C, and so on) and the bottom wire is plugged into the port after it.
+    Encoder { top: u8, reversed: bool },
+}
+
+/// A dedicated (unpowered) tracking wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackingWheelConfig {
+    pub sensor: WheelSensor,
+
+    /// Diameter of the tracking wheel.
+    pub diameter: f64,
+
+    /// Signed distance from the wheel to the robot's center of rotation. Parallel wheels are
+    /// negative on the left, perpendicular wheels are negative towards the back.
+    pub offset: f64,
+}
+
+impl TrackingWheelConfig {
+    pub const fn new(sensor: WheelSensor, diameter: f64, offset: f64) -> Self {
+        Self {
+            sensor,
+            diameter,
+            offset,
+        }
+    }
+
+    /// Adds the wheel's smart port to `ports`. ADI encoders don't use one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the port is already in `ports`.
+    pub const fn ports(&self, ports: Ports) -> Ports {
+        match self.sensor {
+            WheelSensor::Rotation { port, .. } => ports.with(port),
+            WheelSensor::Encoder { .. } => ports,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The wheel's ports may not be used anywhere else in the program.
+    unsafe fn build(self) -> Option<TrackingWheel<TrackingSensor>> {
+        let sensor = match self.sensor {
+            WheelSensor::Rotation { port, direction } => {
+                let sensor = RotationSensor::new(unsafe { SmartPort::new(port) }, direction);
+                if !sensor.is_connected() {
+                    error!("tracking wheel rotation sensor on port {port} isn't connected");
+                    return None;
+                }
+                TrackingSensor::Rotation(sensor)
+            }
+            // ADI encoders can't report whether they're plugged in
+            WheelSensor::Encoder { top, reversed } => TrackingSensor::Encoder {
+                encoder: AdiEncoder::new(unsafe { AdiPort::new(top, None) }, unsafe {
+                    AdiPort::new(top + 1, None)
+                }),
+                reversed,
+            },
+        };
+
+        Some(TrackingWheel::new(sensor, self.diameter, self.offset, None))
+    }
+}
+
+/// Which wheels odometry was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OdometrySource {
+    DriveWheels,
+    TrackingWheels,
+}
+
+/// Either kind of sensor a dedicated tracking wheel can be mounted to, so that wheels on different
+/// kinds of sensors can be passed to tracking together.
+pub enum TrackingSensor {
+    Rotation(RotationSensor),
+    Encoder { encoder: AdiEncoder, reversed: bool },
+}
+
+impl RotarySensor for TrackingSensor {
+    type Error = PortError;
+
+    fn position(&self) -> Result<Angle, Self::Error> {
+        match self {
+            Self::Rotation(sensor) => sensor.position(),
+            Self::Encoder { encoder, reversed } => {
+                let position = encoder.position()?;
+                Ok(if *reversed { -position } else { position })
+            }
+        }
+    }
+}
+
+/// Builds odometry from whichever tracking wheels are configured.
+///
+/// * With one or two parallel wheels and a perpendicular wheel, the robot is tracked along both
+///   axes, so pushes from the side don't corrupt its position.
+/// * With parallel wheels but no perpendicular wheel, the robot is tracked along its forward axis
+///   only.
+/// * With no parallel wheels, the drive wheels are used instead.
+///
+/// If any configured wheel's sensor isn't plugged in, the drive wheels are used so that a loose
+/// wire doesn't leave the robot without odometry. A single parallel wheel can't measure heading by
+/// itself, so the IMU is required in that case. The returned [`OdometrySource`] says which wheels
+/// were used in the end.
+///
+/// # Safety
+///
+/// None of the configured wheels' ports may be used anywhere else in the program.
+pub unsafe fn build<const N: usize>(
+    parallel: &[TrackingWheelConfig],
+    perpendicular: Option<TrackingWheelConfig>,
+    drive_wheels: [TrackingWheel<Rc<RefCell<[Motor; N]>>>; 2],
+    imu: Option<SharedImu>,
+) -> (WheeledTracking, OdometrySource) {
+    assert!(
+        parallel.len() <= 2,
+        "at most two parallel tracking wheels are supported"
+    );
+    if parallel.is_empty() {
+        return drive_wheel_tracking(drive_wheels, imu);
+    }
+
+    let parallel = parallel
+        .iter()
+        .map(|config| unsafe { config.build() })
+        .collect::<Option<Vec<_>>>();
+    let perpendicular = perpendicular.map(|config| unsafe { config.build() });
+
+    // `Some(None)` is a configured perpendicular wheel that isn't plugged in
+    let (Some(parallel), false) = (parallel, matches!(perpendicular, Some(None))) else {
+        warn!("a tracking wheel is missing, falling back to drive wheel tracking");
+        return drive_wheel_tracking(drive_wheels, imu);
+    };
+    let perpendicular = perpendicular.flatten();
+
+    let mut parallel = parallel.into_iter();
+    match (parallel.next(), parallel.next()) {
+        (Some(left), Some(right)) => (
+            dedicated([left, right], perpendicular, imu),
+            OdometrySource::TrackingWheels,
+        ),
+        (Some(wheel), None) if imu.is_some() => (
+            dedicated([wheel], perpendicular, imu),
+            OdometrySource::TrackingWheels,
+        ),
+        _ => {
+            warn!("single parallel tracking wheel without an imu, falling back to drive wheels");
+            drive_wheel_tracking(drive_wheels, imu)
+        }
+    }
+}
+
+fn drive_wheel_tracking<const N: usize>(
+    drive_wheels: [TrackingWheel<Rc<RefCell<[Motor; N]>>>; 2],
+    imu: Option<SharedImu>,
+) -> (WheeledTracking, OdometrySource) {
+    (
+        WheeledTracking::forward_only(ORIGIN, HEADING, drive_wheels, imu),
+        OdometrySource::DriveWheels,
+    )
+}
+
+fn dedicated<const P: usize>(
+    parallel: [TrackingWheel<TrackingSensor>; P],
+    perpendicular: Option<TrackingWheel<TrackingSensor>>,
+    imu: Option<SharedImu>,
+) -> WheeledTracking {
+    match perpendicular {
+        Some(perpendicular) => {
+            info!("tracking with {P} parallel wheel(s) and a perpendicular wheel");
+            WheeledTracking::new(ORIGIN, HEADING, parallel, [perpendicular], imu)
+        }
+        None => {
+            info!("tracking with {P} parallel wheel(s)");
+            WheeledTracking::forward_only(ORIGIN, HEADING, parallel, imu)
+        }
+    }
+}
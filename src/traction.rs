@@ -0,0 +1,163 @@
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use evian::drivetrain::model::{Arcade, Tank};
+use vexide::prelude::*;
+
+use crate::{
+    drive_motors::DriveMotors,
+    imu::{ImuAxis, ImuVelocity},
+};
+
+/// Updates spaced further apart than this are treated as the start of a new drive session.
+const RESET_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Traction Control
+///
+/// A drivetrain model wrapper that sits between whatever is commanding the drivetrain (driver
+/// control or an autonomous motion) and the motors. Raw arcade power causes hard launches that spin
+/// the wheels, which wastes acceleration and corrupts odometry, since the tracking wheels are the
+/// drive wheels.
+///
+/// Traction control does two things to linear power before passing it to the wrapped model:
+///
+/// * Increases in linear power are rate-limited to [`TractionControl::max_acceleration`].
+/// * The robot's speed is estimated from the IMU with [`ImuVelocity`]. When measured wheel
+///   velocity runs more than [`TractionControl::slip_threshold`] ahead of it, the wheels are
+///   slipping and linear power is scaled by [`TractionControl::slip_power_scale`] until they
+///   regain grip.
+///
+/// Angular power is passed through untouched. Tank commands are split into linear and angular
+/// power first, so both arcade and tank drive go through the same limits.
+pub struct TractionControl<M> {
+    /// The wrapped drivetrain model.
+    pub inner: M,
+
+    /// Maximum increase of linear power per second.
+    pub max_acceleration: f64,
+
+    /// How far wheel velocity (in inches per second) can run ahead of IMU-measured velocity before
+    /// the wheels are considered to be slipping.
+    pub slip_threshold: f64,
+
+    /// Multiplier applied to linear power while the wheels are slipping.
+    pub slip_power_scale: f64,
+
+    motors: DriveMotors,
+    imu: Rc<InertialSensor>,
+
+    imu_velocity: ImuVelocity,
+    prev_linear: f64,
+    prev_time: Instant,
+}
+
+impl<M> TractionControl<M> {
+    /// Wraps a drivetrain model with traction control.
+    ///
+    /// # Constants
+    ///
+    /// * `max_acceleration` - Maximum increase of linear power per second.
+    /// * `slip_threshold` - How far wheel velocity can run ahead of IMU-measured velocity before
+    ///   the wheels are considered to be slipping.
+    /// * `slip_power_scale` - Multiplier applied to linear power while the wheels are slipping.
+    /// * `imu_axis` - Which of the IMU's axes points towards the front of the robot.
+    pub fn new(
+        inner: M,
+        motors: DriveMotors,
+        imu: Rc<InertialSensor>,
+        max_acceleration: f64,
+        slip_threshold: f64,
+        slip_power_scale: f64,
+        imu_axis: ImuAxis,
+    ) -> Self {
+        Self {
+            inner,
+            max_acceleration,
+            slip_threshold,
+            slip_power_scale,
+            motors,
+            imu,
+            imu_velocity: ImuVelocity::new(imu_axis),
+            prev_linear: 0.0,
+            prev_time: Instant::now(),
+        }
+    }
+
+    /// Checks wheel velocity against the IMU velocity estimate, returning `true` if the wheels are
+    /// slipping.
+    fn update_slip(&mut self, dt: f64) -> bool {
+        let Some(wheel_velocity) = self.motors.wheel_velocity() else {
+            return false;
+        };
+
+        self.imu_velocity.integrate(&self.imu, dt);
+
+        let slipping =
+            wheel_velocity.abs() - self.imu_velocity.velocity().abs() > self.slip_threshold;
+        if !slipping {
+            self.imu_velocity.blend(wheel_velocity);
+        }
+
+        slipping
+    }
+
+    fn limit_acceleration(&self, linear: f64, dt: f64) -> f64 {
+        // reversing direction is allowed to drop to zero immediately, but not to launch backwards
+        let base = if linear * self.prev_linear < 0.0 {
+            0.0
+        } else {
+            self.prev_linear
+        };
+
+        if linear.abs() <= base.abs() {
+            return linear;
+        }
+
+        let max_step = self.max_acceleration * dt;
+        base + (linear - base).clamp(-max_step, max_step)
+    }
+
+    /// Runs a linear power command through the acceleration limit and slip detection.
+    fn limit_linear(&mut self, throttle: f64) -> f64 {
+        let now = Instant::now();
+        let elapsed = now - self.prev_time;
+        self.prev_time = now;
+
+        if elapsed > RESET_INTERVAL {
+            // nothing has driven the drivetrain for a while, so the old estimate can't be trusted
+            self.imu_velocity
+                .reset(self.motors.wheel_velocity().unwrap_or_default());
+            self.prev_linear = 0.0;
+        }
+        let dt = elapsed.min(RESET_INTERVAL).as_secs_f64();
+
+        let mut linear = self.limit_acceleration(throttle, dt);
+        if self.update_slip(dt) {
+            linear *= self.slip_power_scale;
+        }
+        self.prev_linear = linear;
+
+        linear
+    }
+}
+
+impl<M: Arcade> Arcade for TractionControl<M> {
+    type Error = M::Error;
+
+    fn drive_arcade(&mut self, throttle: f64, steer: f64) -> Result<(), Self::Error> {
+        let linear = self.limit_linear(throttle);
+        self.inner.drive_arcade(linear, steer)
+    }
+}
+
+impl<M: Tank> Tank for TractionControl<M> {
+    type Error = M::Error;
+
+    fn drive_tank(&mut self, left: f64, right: f64) -> Result<(), Self::Error> {
+        let linear = self.limit_linear((left + right) / 2.0);
+        let angular = (left - right) / 2.0;
+        self.inner.drive_tank(linear + angular, linear - angular)
+    }
+}
@@ -0,0 +1,324 @@
+//! Time-parameterized trajectories for differential drives.
+//!
+//! A trajectory says where the robot should be, which way it should face, and how fast it should
+//! be moving at every moment, so a controller like RAMSETE can track the whole motion instead of
+//! just the path. It's generated from a sampled path (see `path.rs`) by finding the fastest speed
+//! at each point that stays within [`Constraints`], then integrating that speed into time.
+//!
+//! Positions are in inches, headings in radians counterclockwise from the x axis, and times in
+//! seconds.
+//!
+//! This module only depends on `std` so that it can be shared with `host/`.
+
+use std::f64::consts::PI;
+
+use crate::{ekf::wrap, path::PathPoint};
+
+/// Nominal distance between the left and right drive wheels in inches. The robot replaces it with
+/// the calibrated one when following a trajectory.
+pub const TRACK_WIDTH: f64 = 11.5;
+
+/// The limits trajectories are generated with, on the robot and in `trajectory-gen`, so that
+/// generated trajectories match what the robot drives.
+pub const CONSTRAINTS: Constraints = Constraints {
+    max_velocity: 20.0,
+    max_acceleration: 40.0,
+    max_lateral_acceleration: 30.0,
+    track_width: TRACK_WIDTH,
+};
+
+/// Limits on how a trajectory can be driven.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    /// Fastest the robot can drive in inches per second. Each path point's speed is a fraction of
+    /// this.
+    pub max_velocity: f64,
+
+    /// Fastest the robot can speed up or slow down in inches per second squared.
+    pub max_acceleration: f64,
+
+    /// Fastest the robot can be pulled sideways while turning in inches per second squared, which
+    /// limits speed through tight curves.
+    pub max_lateral_acceleration: f64,
+
+    /// Distance between the left and right drive wheels. On a curve the outside wheel drives
+    /// faster than the robot does, and it still has to stay under the max velocity.
+    pub track_width: f64,
+}
+
+/// Where the robot should be at one moment.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct State {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+    pub heading: f64,
+
+    /// Forward velocity, negative while driving backwards.
+    pub velocity: f64,
+
+    /// Counterclockwise angular velocity.
+    pub angular_velocity: f64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Trajectory {
+    pub states: Vec<State>,
+}
+
+impl Trajectory {
+    /// Generates the fastest trajectory along `path` that stays within `constraints`, starting and
+    /// ending at rest. If `reverse` is set the robot drives the path backwards.
+    pub fn generate(path: &[PathPoint], constraints: Constraints, reverse: bool) -> Self {
+        if path.is_empty() {
+            return Self::default();
+        }
+
+        let curvatures = (0..path.len())
+            .map(|i| curvature(path, i))
+            .collect::<Vec<_>>();
+
+        let mut velocities = path
+            .iter()
+            .zip(&curvatures)
+            .map(|(point, curvature)| {
+                let lateral = (constraints.max_lateral_acceleration / curvature.abs()).sqrt();
+                let wheel = constraints.max_velocity
+                    / (1.0 + curvature.abs() * constraints.track_width / 2.0);
+                (point.speed * constraints.max_velocity)
+                    .min(lateral)
+                    .min(wheel)
+            })
+            .collect::<Vec<_>>();
+
+        // start and end at rest, and never speed up or slow down faster than the robot can
+        velocities[0] = 0.0;
+        if let Some(last) = velocities.last_mut() {
+            *last = 0.0;
+        }
+        for i in 1..path.len() {
+            let reachable = (velocities[i - 1].powi(2)
+                + 2.0 * constraints.max_acceleration * step(path, i))
+            .sqrt();
+            velocities[i] = velocities[i].min(reachable);
+        }
+        for i in (0..path.len() - 1).rev() {
+            let stoppable = (velocities[i + 1].powi(2)
+                + 2.0 * constraints.max_acceleration * step(path, i + 1))
+            .sqrt();
+            velocities[i] = velocities[i].min(stoppable);
+        }
+
+        let mut states = Vec::with_capacity(path.len());
+        let mut time = 0.0;
+        let mut previous_heading: Option<f64> = None;
+        for (i, point) in path.iter().enumerate() {
+            if i > 0 {
+                let average = (velocities[i - 1] + velocities[i]) / 2.0;
+                if average > 0.0 {
+                    time += step(path, i) / average;
+                }
+            }
+
+            let mut heading = tangent(path, i);
+            if reverse {
+                heading += PI;
+            }
+            // keep the heading continuous so that it can be interpolated
+            if let Some(previous) = previous_heading {
+                heading = previous + wrap(heading - previous);
+            }
+            previous_heading = Some(heading);
+
+            let speed = velocities[i];
+            states.push(State {
+                time,
+                x: point.x,
+                y: point.y,
+                heading,
+                velocity: if reverse { -speed } else { speed },
+                angular_velocity: curvatures[i] * speed,
+            });
+        }
+
+        Self { states }
+    }
+
+    /// How long the trajectory takes to drive.
+    pub fn duration(&self) -> f64 {
+        self.states.last().map_or(0.0, |state| state.time)
+    }
+
+    /// Where the robot should be `time` seconds in, interpolated between the generated states.
+    pub fn sample(&self, time: f64) -> State {
+        let next = self.states.partition_point(|state| state.time < time);
+        match (
+            next.checked_sub(1).and_then(|i| self.states.get(i)),
+            self.states.get(next),
+        ) {
+            (Some(a), Some(b)) => {
+                let t = (time - a.time) / (b.time - a.time).max(f64::EPSILON);
+                let lerp = |a: f64, b: f64| a + (b - a) * t;
+                State {
+                    time,
+                    x: lerp(a.x, b.x),
+                    y: lerp(a.y, b.y),
+                    heading: lerp(a.heading, b.heading),
+                    velocity: lerp(a.velocity, b.velocity),
+                    angular_velocity: lerp(a.angular_velocity, b.angular_velocity),
+                }
+            }
+            (Some(state), None) | (None, Some(state)) => *state,
+            (None, None) => State::default(),
+        }
+    }
+}
+
+/// Distance from the point before `i` to `i`.
+fn step(path: &[PathPoint], i: usize) -> f64 {
+    path[i].distance - path[i - 1].distance
+}
+
+/// Direction of travel at point `i`.
+fn tangent(path: &[PathPoint], i: usize) -> f64 {
+    let a = path[i.saturating_sub(1)];
+    let b = path[(i + 1).min(path.len() - 1)];
+    (b.y - a.y).atan2(b.x - a.x)
+}
+
+/// Signed curvature at point `i` (positive curving counterclockwise), from the circle through it
+/// and its neighbors.
+fn curvature(path: &[PathPoint], i: usize) -> f64 {
+    if i == 0 || i + 1 >= path.len() {
+        return 0.0;
+    }
+
+    let [a, b, c] = [path[i - 1], path[i], path[i + 1]];
+    let cross = (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x);
+    let lengths =
+        (b.x - a.x).hypot(b.y - a.y) * (c.x - b.x).hypot(c.y - b.y) * (c.x - a.x).hypot(c.y - a.y);
+
+    if lengths > 0.0 {
+        2.0 * cross / lengths
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{self, PathSegment};
+
+    // room for rounding when comparing against the constraints
+    const TOLERANCE: f64 = 1e-6;
+
+    /// A straight run into an s-bend, then a slower straight.
+    fn path() -> Vec<PathPoint> {
+        path::sample(
+            &[
+                PathSegment::line([-48.0, -48.0], [-24.0, -48.0], 1.0),
+                PathSegment {
+                    controls: vec![[-24.0, -48.0], [0.0, -48.0], [0.0, 0.0], [24.0, 0.0]],
+                    speed: 1.0,
+                },
+                PathSegment::line([24.0, 0.0], [48.0, 0.0], 0.5),
+            ],
+            path::SPACING,
+        )
+    }
+
+    #[test]
+    fn starts_and_ends_at_rest() {
+        let trajectory = Trajectory::generate(&path(), CONSTRAINTS, false);
+
+        let first = trajectory.states.first().unwrap();
+        let last = trajectory.states.last().unwrap();
+        assert_eq!(first.velocity, 0.0);
+        assert_eq!(last.velocity, 0.0);
+        assert_eq!(first.time, 0.0);
+        assert!((last.x - 48.0).abs() < TOLERANCE && last.y.abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn stays_within_velocity_limits() {
+        let path = path();
+        let trajectory = Trajectory::generate(&path, CONSTRAINTS, false);
+
+        for (i, (state, point)) in trajectory.states.iter().zip(&path).enumerate() {
+            let curvature = curvature(&path, i).abs();
+            let outside_wheel = state.velocity * (1.0 + curvature * CONSTRAINTS.track_width / 2.0);
+            let lateral = state.velocity.powi(2) * curvature;
+
+            assert!(
+                state.velocity <= point.speed * CONSTRAINTS.max_velocity + TOLERANCE,
+                "point {i}: {state:?}"
+            );
+            assert!(
+                outside_wheel <= CONSTRAINTS.max_velocity + TOLERANCE,
+                "point {i}: outside wheel at {outside_wheel}"
+            );
+            assert!(
+                lateral <= CONSTRAINTS.max_lateral_acceleration + TOLERANCE,
+                "point {i}: lateral acceleration {lateral}"
+            );
+        }
+
+        // the limits should actually be reached somewhere on a path this long
+        let fastest = trajectory
+            .states
+            .iter()
+            .map(|state| state.velocity)
+            .fold(0.0, f64::max);
+        assert!(
+            fastest > 0.9 * CONSTRAINTS.max_velocity,
+            "fastest {fastest}"
+        );
+    }
+
+    #[test]
+    fn stays_within_acceleration_limit() {
+        let trajectory = Trajectory::generate(&path(), CONSTRAINTS, false);
+
+        for pair in trajectory.states.windows(2) {
+            let [a, b] = [pair[0], pair[1]];
+            let dt = b.time - a.time;
+            assert!(dt > 0.0, "time doesn't advance from {a:?} to {b:?}");
+
+            let acceleration = (b.velocity - a.velocity) / dt;
+            assert!(
+                acceleration.abs() <= CONSTRAINTS.max_acceleration + TOLERANCE,
+                "{acceleration} from {a:?} to {b:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn reverse_drives_backwards() {
+        let forward = Trajectory::generate(&path(), CONSTRAINTS, false);
+        let reverse = Trajectory::generate(&path(), CONSTRAINTS, true);
+
+        assert_eq!(forward.duration(), reverse.duration());
+        for (forward, reverse) in forward.states.iter().zip(&reverse.states) {
+            assert_eq!(reverse.velocity, -forward.velocity);
+            assert!((wrap(reverse.heading - forward.heading).abs() - PI).abs() < TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn sample_interpolates_between_states() {
+        let trajectory = Trajectory::generate(&path(), CONSTRAINTS, false);
+        let [a, b] = [trajectory.states[10], trajectory.states[11]];
+
+        let middle = trajectory.sample((a.time + b.time) / 2.0);
+        assert!((middle.x - (a.x + b.x) / 2.0).abs() < TOLERANCE);
+        assert!((middle.velocity - (a.velocity + b.velocity) / 2.0).abs() < TOLERANCE);
+
+        // past either end holds the end state
+        assert_eq!(trajectory.sample(-1.0), trajectory.states[0]);
+        assert_eq!(
+            trajectory.sample(trajectory.duration() + 1.0),
+            *trajectory.states.last().unwrap()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    cell::Cell,
+    fmt,
+    future::{Future, poll_fn},
+    pin::pin,
+    rc::Rc,
+    task::Poll,
+    time::{Duration, Instant},
+};
+
+use evian::math::{Angle, Vec2};
+use log::{info, warn};
+use vexide::prelude::*;
+
+use crate::{
+    drive_motors::DriveMotors,
+    ekf::{Pose, wrap},
+    fused_tracking::TrackingReader,
+    tip_guard::Tilt,
+};
+
+/// How a motion ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The motion finished on its own, normally by getting within its tolerances.
+    Settled,
+
+    /// The motion took longer than its timeout and was stopped.
+    TimedOut,
+
+    /// The drive wheels stopped turning before the motion finished, so something is in the way.
+    Stalled,
+
+    /// The tip guard had to take over the drivetrain, so the motion was given up once the robot
+    /// was back down.
+    Aborted,
+}
+
+/// What a motion was trying to reach, used to measure how far off it ended up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    /// Driving a distance along the heading the robot had when the motion started.
+    Distance(f64),
+
+    /// Turning to a heading.
+    Heading(Angle),
+
+    /// Turning to face a point.
+    Facing(Vec2<f64>),
+
+    /// Driving to a point.
+    Point(Vec2<f64>),
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Distance(distance) => write!(f, "drive {distance:.1} in"),
+            Self::Heading(heading) => write!(f, "turn to {:.1} deg", heading.as_degrees()),
+            Self::Facing(point) => write!(f, "turn to face ({:.1}, {:.1})", point.x, point.y),
+            Self::Point(point) => write!(f, "move to ({:.1}, {:.1})", point.x, point.y),
+        }
+    }
+}
+
+/// The result of a motion run by a [`Watchdog`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionResult {
+    pub outcome: Outcome,
+
+    /// How far from the target the robot ended up, in inches for drives and radians for turns.
+    pub error: f64,
+
+    /// How long the motion ran.
+    pub duration: Duration,
+}
+
+impl MotionResult {
+    /// Whether the motion finished on its own.
+    pub fn settled(&self) -> bool {
+        self.outcome == Outcome::Settled
+    }
+}
+
+/// Motion Watchdog
+///
+/// Runs autonomous motions with a timeout so that a robot that's stuck on something can't hang
+/// the rest of the route, and reports how each one ended so routes can log it or react to it.
+///
+/// While a motion runs, the watchdog stops it if
+///
+/// * it runs longer than its timeout,
+/// * both sides of the drivetrain stay slower than [`Watchdog::stall_speed`] for
+///   [`Watchdog::stall_time`], or
+/// * the tip guard takes over, in which case the motion is given up once the robot is back down.
+///
+/// A motion the watchdog stops is dropped and the drive motors are braked.
+#[derive(Clone)]
+pub struct Watchdog {
+    /// Wheel speed in inches per second below which a side of the drivetrain isn't moving.
+    pub stall_speed: f64,
+
+    /// How long the drivetrain can stay still before the motion counts as stalled.
+    pub stall_time: Duration,
+
+    reader: TrackingReader,
+    motors: DriveMotors,
+    tilt: Rc<Cell<Tilt>>,
+}
+
+impl Watchdog {
+    /// Creates a watchdog that reads the pose from `reader`, wheel speeds from `motors` and the
+    /// tip guard's state from `tilt`.
+    ///
+    /// # Constants
+    ///
+    /// * `stall_speed` - Wheel speed in inches per second below which a side isn't moving.
+    /// * `stall_time` - How long the drivetrain can stay still before the motion is stalled.
+    pub fn new(
+        reader: TrackingReader,
+        motors: DriveMotors,
+        tilt: Rc<Cell<Tilt>>,
+        stall_speed: f64,
+        stall_time: Duration,
+    ) -> Self {
+        Self {
+            stall_speed,
+            stall_time,
+            reader,
+            motors,
+            tilt,
+        }
+    }
+
+    /// Runs `motion` towards `target` until it finishes or is stopped, giving it `timeout` to
+    /// finish, then logs and returns how it went.
+    pub async fn watch(
+        &self,
+        target: Target,
+        timeout: Duration,
+        motion: impl Future<Output = ()>,
+    ) -> MotionResult {
+        let start = self.reader.pose();
+        let start_time = Instant::now();
+        let mut moving_time = start_time;
+        let mut tipped = false;
+        let mut motion = pin!(motion);
+
+        // motions sleep between updates, so this is polled every time they update
+        let outcome = poll_fn(|cx| {
+            if motion.as_mut().poll(cx).is_ready() {
+                // motions with their own timeout finish by themselves when it runs out
+                return Poll::Ready(if start_time.elapsed() >= timeout {
+                    Outcome::TimedOut
+                } else {
+                    Outcome::Settled
+                });
+            }
+
+            let now = Instant::now();
+            if now - start_time > timeout {
+                return Poll::Ready(Outcome::TimedOut);
+            }
+
+            match self.tilt.get() {
+                Tilt::Tipping(_) => tipped = true,
+                // wait for the tip guard to bring the robot back down before giving up
+                _ if tipped => return Poll::Ready(Outcome::Aborted),
+                _ => {}
+            }
+
+            let (left, right) = self.motors.side_velocities();
+            let moving = [left, right]
+                .into_iter()
+                .flatten()
+                .any(|velocity| velocity.abs() >= self.stall_speed);
+            // a drivetrain that can't be read isn't known to be stalled
+            if moving || (left.is_none() && right.is_none()) || tipped {
+                moving_time = now;
+            } else if now - moving_time > self.stall_time {
+                return Poll::Ready(Outcome::Stalled);
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        // a motion that's dropped part way through leaves the motors running
+        if outcome != Outcome::Settled
+            && let Err(e) = self.motors.brake(BrakeMode::Brake)
+        {
+            warn!("couldn't stop the drivetrain after a motion was stopped: {e}");
+        }
+
+        let result = MotionResult {
+            outcome,
+            error: error(target, start, self.reader.pose()),
+            duration: start_time.elapsed(),
+        };
+
+        let description = match outcome {
+            Outcome::Settled => "settled",
+            Outcome::TimedOut => "timed out",
+            Outcome::Stalled => "stalled",
+            Outcome::Aborted => "aborted",
+        };
+        let message = format!(
+            "{target} {description} after {:.2} s, {:.2} off",
+            result.duration.as_secs_f64(),
+            result.error,
+        );
+        if result.settled() {
+            info!("{message}");
+        } else {
+            warn!("{message}");
+        }
+
+        result
+    }
+}
+
+/// How far `pose` is from `target`, for a motion that started at `start`.
+fn error(target: Target, start: Pose, pose: Pose) -> f64 {
+    let heading_error = |heading: f64| wrap(heading - pose.heading).abs();
+    let distance = |x: f64, y: f64| (x - pose.x).hypot(y - pose.y);
+
+    match target {
+        // a distance is driven along the starting heading, so it's measured to where that ends
+        Target::Distance(travel) => distance(
+            start.x + start.heading.cos() * travel,
+            start.y + start.heading.sin() * travel,
+        ),
+        Target::Heading(heading) => heading_error(heading.as_radians()),
+        Target::Facing(point) => heading_error((point.y - pose.y).atan2(point.x - pose.x)),
+        Target::Point(point) => distance(point.x, point.y),
+    }
+}